@@ -0,0 +1,130 @@
+use rand::Rng;
+use rand_seeder::Seeder;
+
+use crate::environment::Tree;
+use crate::map::Chunk;
+use crate::monster::MonsterKind;
+
+/// How many chunks there are between two points of the biome noise grid. The higher it is, the
+/// bigger the biomes are.
+const BIOME_SCALE: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Biome {
+    Meadow,
+    PineForest,
+    HauntedGraveyard,
+}
+
+impl Biome {
+    pub fn for_chunk(chunk: Chunk) -> Self {
+        // The starting town is always in a meadow.
+        if chunk == Chunk::ORIGIN {
+            return Self::Meadow;
+        }
+        let value = noise(chunk.x, chunk.y);
+        if value < 0.45 {
+            Self::Meadow
+        } else if value < 0.62 {
+            Self::PineForest
+        } else {
+            Self::HauntedGraveyard
+        }
+    }
+
+    /// The trees which can appear in this biome. The more a tree is present in the list, the more
+    /// likely it is to be picked.
+    pub fn trees(self) -> &'static [Tree] {
+        match self {
+            Self::Meadow => &[Tree::Normal, Tree::Normal, Tree::Normal, Tree::Sapin],
+            Self::PineForest => &[
+                Tree::Sapin,
+                Tree::Sapin,
+                Tree::Sapin,
+                Tree::Normal,
+                Tree::Dead,
+            ],
+            Self::HauntedGraveyard => &[Tree::Scary, Tree::Scary, Tree::Dead],
+        }
+    }
+
+    pub fn nb_trees(self) -> usize {
+        match self {
+            Self::Meadow => 30,
+            Self::PineForest => 120,
+            Self::HauntedGraveyard => 40,
+        }
+    }
+
+    pub fn nb_bushes(self) -> usize {
+        match self {
+            Self::Meadow => 50,
+            Self::PineForest => 30,
+            Self::HauntedGraveyard => 10,
+        }
+    }
+
+    pub fn nb_grass_patches(self) -> usize {
+        match self {
+            Self::Meadow => 40,
+            Self::PineForest => 15,
+            Self::HauntedGraveyard => 5,
+        }
+    }
+
+    /// Chance (in percent) for each cell of a grass patch to actually have grass.
+    pub fn grass_density(self) -> u8 {
+        match self {
+            Self::Meadow => 80,
+            Self::PineForest => 60,
+            Self::HauntedGraveyard => 40,
+        }
+    }
+
+    /// The monsters which can appear in this biome. Works the same as `trees`.
+    pub fn monsters(self) -> &'static [MonsterKind] {
+        match self {
+            Self::Meadow => &[],
+            Self::PineForest | Self::HauntedGraveyard => &[MonsterKind::Skeleton],
+        }
+    }
+
+    pub fn nb_dungeon_entrances(self) -> usize {
+        match self {
+            Self::Meadow | Self::PineForest => 0,
+            Self::HauntedGraveyard => 1,
+        }
+    }
+
+    pub fn nb_monsters(self) -> usize {
+        match self {
+            Self::Meadow => 0,
+            Self::PineForest => 2,
+            Self::HauntedGraveyard => 10,
+        }
+    }
+}
+
+fn grid_value(x: i32, y: i32) -> f32 {
+    let mut rng: crate::SeedType =
+        Seeder::from(&format!("{};biome;{};{}", crate::SEED, x, y)).make_rng();
+    rng.gen()
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3. - 2. * t)
+}
+
+// Value noise: random values are generated on a grid (seeded by `SEED`) and interpolated
+// between them, so neighbouring chunks are likely to be in the same biome.
+fn noise(x: i32, y: i32) -> f32 {
+    let grid_x = x.div_euclid(BIOME_SCALE);
+    let grid_y = y.div_euclid(BIOME_SCALE);
+    let tx = smoothstep(x.rem_euclid(BIOME_SCALE) as f32 / BIOME_SCALE as f32);
+    let ty = smoothstep(y.rem_euclid(BIOME_SCALE) as f32 / BIOME_SCALE as f32);
+
+    let top = grid_value(grid_x, grid_y) * (1. - tx) + grid_value(grid_x + 1, grid_y) * tx;
+    let bottom =
+        grid_value(grid_x, grid_y + 1) * (1. - tx) + grid_value(grid_x + 1, grid_y + 1) * tx;
+    top * (1. - ty) + bottom * ty
+}
@@ -1,11 +1,32 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use rand_seeder::Seeder;
+use serde::Deserialize;
 
 use crate::character::{
     Character, CharacterAnimationInfo, CharacterAnimationType, CharacterKind, CharacterPoints,
 };
-
-#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+use crate::map::Chunk;
+use crate::map_file::{MapObject, MapObjectKind};
+
+const INSIDE_BASE_WIDTH: f32 = 237.;
+const INSIDE_BASE_HEIGHT: f32 = 160.;
+/// By how much the inside of a building can be bigger than the base size.
+const INSIDE_SIZE_STEP: f32 = 32.;
+const INSIDE_MAX_RANDOM_FURNITURES: usize = 4;
+/// Chance (in percent) for a house to have a carpet.
+const INSIDE_CARPET_CHANCE: u8 = 50;
+/// Furnitures which can be randomly added inside buildings.
+const INSIDE_RANDOM_FURNITURES: &[Furniture] = &[
+    Furniture::SmallTable,
+    Furniture::Stool,
+    Furniture::Stool,
+    Furniture::Crate,
+    Furniture::Crate,
+];
+
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum Building {
     House,
     GeneralShop,
@@ -310,7 +331,7 @@ pub struct Door;
 #[derive(Debug, Component)]
 pub struct EnterArea;
 
-fn insert_furniture<C: Component>(
+pub fn insert_furniture<C: Bundle>(
     commands: &mut Commands,
     furnitures_texture: Handle<Image>,
     furniture: Furniture,
@@ -390,6 +411,14 @@ const GENERAL_SHOP_HEIGHT_F: f32 = GENERAL_SHOP_HEIGHT as f32;
 const GENERAL_SHOP_WIDTH_F: f32 = GENERAL_SHOP_WIDTH as f32;
 const WEAPON_SHOP_HEIGHT: u32 = 106;
 const WEAPON_SHOP_WIDTH: u32 = 110;
+/// Space kept free in front of a building so its entrance can be reached.
+const ENTRANCE_SIZE: f32 = 30.;
+
+fn footprint_with_entrance(x: f32, y: f32, size: Vec2) -> Rect {
+    let mut footprint = Rect::from_center_size(Vec2::new(x, y), size);
+    footprint.min.y -= ENTRANCE_SIZE;
+    footprint
+}
 
 fn insert_shop(
     layout: Handle<TextureAtlasLayout>,
@@ -398,13 +427,15 @@ fn insert_shop(
     building: Building,
     x: f32,
     y: f32,
-) {
+    chunk: Chunk,
+) -> Rect {
     let start_index = building.get_start_index();
 
     commands
         .spawn((
             building,
             crate::game::OutsideWorld,
+            chunk,
             SpriteBundle {
                 texture: texture.clone(),
                 transform: Transform::from_xyz(x, y, 0.0),
@@ -469,6 +500,11 @@ fn insert_shop(
                 TransformBundle::from(Transform::from_xyz(0.0, -34.0, 0.0)),
             ));
         });
+    let size = match building {
+        Building::WeaponShop => Vec2::new(WEAPON_SHOP_WIDTH as f32, WEAPON_SHOP_HEIGHT as f32),
+        _ => Vec2::new(GENERAL_SHOP_WIDTH_F, GENERAL_SHOP_HEIGHT_F),
+    };
+    footprint_with_entrance(x, y, size)
 }
 
 fn insert_house(
@@ -477,7 +513,8 @@ fn insert_house(
     commands: &mut Commands,
     x: f32,
     y: f32,
-) {
+    chunk: Chunk,
+) -> Rect {
     const TOP_SIZE: f32 = 60.;
     const BOTTOM_SIZE: f32 = 88. - TOP_SIZE;
     const WIDTH: f32 = 80.;
@@ -486,6 +523,7 @@ fn insert_house(
         .spawn((
             Building::House,
             crate::game::OutsideWorld,
+            chunk,
             TextureAtlas {
                 index: false as _, // door open is false so index is 0
                 layout: layout.clone(),
@@ -559,13 +597,32 @@ fn insert_house(
                 TransformBundle::from(Transform::from_xyz(0.0, Y - 36.0, 0.0)),
             ));
         });
+    footprint_with_entrance(
+        x,
+        y - TOP_SIZE / 2.,
+        Vec2::new(WIDTH, TOP_SIZE + BOTTOM_SIZE),
+    )
 }
 
 pub fn spawn_buildings(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlasLayout>,
+    chunk: Chunk,
+    objects: &[&MapObject],
+    obstacles: &mut Vec<Rect>,
 ) {
+    let buildings = objects
+        .iter()
+        .filter_map(|object| match object.kind {
+            MapObjectKind::Building(building) => Some((building, object.x, object.y)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if buildings.is_empty() {
+        return;
+    }
+
     let house_texture = asset_server.load("textures/house.png");
     let mut house_texture_atlas = TextureAtlasLayout::new_empty(UVec2::new(160, 88));
     // We split the top from the bottom part.
@@ -577,23 +634,6 @@ pub fn spawn_buildings(
 
     let house_texture_atlas_handle = texture_atlases.add(house_texture_atlas);
 
-    for nb in 0..2 {
-        insert_house(
-            house_texture_atlas_handle.clone(),
-            house_texture.clone(),
-            &mut commands,
-            -100.,
-            (nb as f32) * 150.,
-        );
-        insert_house(
-            house_texture_atlas_handle.clone(),
-            house_texture.clone(),
-            &mut commands,
-            -400.,
-            (nb as f32) * 150.,
-        );
-    }
-
     let shops_texture = asset_server.load("textures/shops.png");
     let shops_texture_atlas = TextureAtlasLayout::from_grid(
         UVec2::new(GENERAL_SHOP_WIDTH, GENERAL_SHOP_HEIGHT),
@@ -603,22 +643,28 @@ pub fn spawn_buildings(
         None,
     );
     let shops_texture_atlas_handle = texture_atlases.add(shops_texture_atlas);
-    insert_shop(
-        shops_texture_atlas_handle.clone(),
-        shops_texture.clone(),
-        &mut commands,
-        Building::GeneralShop,
-        -220.,
-        270.,
-    );
-    insert_shop(
-        shops_texture_atlas_handle,
-        shops_texture,
-        &mut commands,
-        Building::WeaponShop,
-        -100.,
-        270.,
-    );
+
+    for (building, x, y) in buildings {
+        obstacles.push(match building {
+            Building::House => insert_house(
+                house_texture_atlas_handle.clone(),
+                house_texture.clone(),
+                commands,
+                x,
+                y,
+                chunk,
+            ),
+            Building::GeneralShop | Building::WeaponShop => insert_shop(
+                shops_texture_atlas_handle.clone(),
+                shops_texture.clone(),
+                commands,
+                building,
+                x,
+                y,
+                chunk,
+            ),
+        });
+    }
 }
 
 pub fn spawn_inside_building(
@@ -638,8 +684,16 @@ pub fn spawn_inside_building(
     let x = app_state.pos.x;
     let y = app_state.pos.y;
 
-    let width = 237.;
-    let height = 160.;
+    let mut rng: crate::SeedType = Seeder::from(&format!(
+        "{};building;{}",
+        crate::SEED,
+        app_state.building_hash
+    ))
+    .make_rng();
+    let width = INSIDE_BASE_WIDTH + INSIDE_SIZE_STEP * rng.gen_range(0..=2) as f32;
+    let height = INSIDE_BASE_HEIGHT + INSIDE_SIZE_STEP * rng.gen_range(0..=2) as f32;
+    // Half of the exit door opening in the bottom wall.
+    let door = 13.;
 
     commands
         .spawn((
@@ -662,32 +716,34 @@ pub fn spawn_inside_building(
         .with_children(|children| {
             // The left and right walls.
             children.spawn((
-                Collider::cuboid(16., 57.),
+                Collider::cuboid(16., height / 2. - 23.),
                 CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
-                TransformBundle::from(Transform::from_xyz(-106.0, 0.0, 0.0)),
+                TransformBundle::from(Transform::from_xyz(12.5 - width / 2., 0.0, 0.0)),
             ));
             children.spawn((
-                Collider::cuboid(16., 57.),
+                Collider::cuboid(16., height / 2. - 23.),
                 CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
-                TransformBundle::from(Transform::from_xyz(106.0, 0.0, 0.0)),
+                TransformBundle::from(Transform::from_xyz(width / 2. - 12.5, 0.0, 0.0)),
             ));
             // The top wall.
             children.spawn((
-                Collider::cuboid(92., 16.),
+                Collider::cuboid(width / 2. - 26.5, 16.),
                 CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
-                TransformBundle::from(Transform::from_xyz(0.0, 66.0, 0.0)),
+                TransformBundle::from(Transform::from_xyz(0.0, height / 2. - 14., 0.0)),
             ));
+            let bottom_half_width = (width / 2. + 4.5 - door) / 2.;
+            let bottom_x = door + bottom_half_width;
             // The bottom wall (left part).
             children.spawn((
-                Collider::cuboid(55., 16.),
+                Collider::cuboid(bottom_half_width, 16.),
                 CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
-                TransformBundle::from(Transform::from_xyz(-68.0, -65.0, 0.0)),
+                TransformBundle::from(Transform::from_xyz(-bottom_x, 15. - height / 2., 0.0)),
             ));
             // The bottom wall (right part).
             children.spawn((
-                Collider::cuboid(55., 16.),
+                Collider::cuboid(bottom_half_width, 16.),
                 CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
-                TransformBundle::from(Transform::from_xyz(68.0, -65.0, 0.0)),
+                TransformBundle::from(Transform::from_xyz(bottom_x, 15. - height / 2., 0.0)),
             ));
             // The exit door.
             children.spawn((
@@ -695,11 +751,25 @@ pub fn spawn_inside_building(
                 CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
                 Sensor,
                 EnterArea,
-                TransformBundle::from(Transform::from_xyz(0., -70.0, 0.0)),
+                TransformBundle::from(Transform::from_xyz(0., 10. - height / 2., 0.0)),
             ));
         });
 
     let furnitures_texture = asset_server.load("textures/furnitures.png");
+    // The part of the room where furnitures can be put.
+    let floor = Rect::new(
+        x - width / 2. + 29.,
+        y - height / 2. + 31.,
+        x + width / 2. - 29.,
+        y + height / 2. - 30.,
+    );
+    // We keep the way between the exit door and the center of the room free.
+    let mut used = vec![Rect::new(
+        x - door * 2.,
+        floor.min.y,
+        x + door * 2.,
+        y + 10.,
+    )];
 
     if matches!(building, Building::WeaponShop | Building::GeneralShop) {
         let desk_y = y + 5.;
@@ -735,6 +805,13 @@ pub fn spawn_inside_building(
             );
         }
         let pos_in_image = Furniture::Desk.pos_in_image();
+        // The desk and the vendor behind it.
+        used.push(Rect::new(
+            x - pos_in_image.width() / 2.,
+            desk_y - pos_in_image.height() / 2.,
+            x + pos_in_image.width() / 2.,
+            floor.max.y,
+        ));
         crate::vendor::spawn_vendor(
             &mut commands,
             asset_server,
@@ -745,28 +822,79 @@ pub fn spawn_inside_building(
             is_weapon_shop,
         );
     } else {
+        let bed = Furniture::Bed.pos_in_image();
+        let bed_x = floor.min.x + bed.width() / 2. + 1.;
+        let bed_y = floor.max.y - bed.height() / 2. - 1.;
         insert_furniture(
             &mut commands,
             furnitures_texture.clone(),
             Furniture::Bed,
-            x - 73.,
-            y - 20.,
+            bed_x,
+            bed_y - bed.height() / 4.,
             crate::game::InsideHouse,
             false,
         );
+        used.push(Rect::from_center_size(
+            Vec2::new(bed_x, bed_y),
+            Vec2::new(bed.width(), bed.height()),
+        ));
         insert_furniture(
             &mut commands,
             furnitures_texture.clone(),
             Furniture::DoorCarpet,
             x,
-            y - 41.,
+            y - height / 2. + 39.,
             crate::game::InsideHouse,
             false,
         );
+        if rng.gen_range(0..100) < INSIDE_CARPET_CHANCE {
+            let dimension = CarpetDimension {
+                width: rng.gen_range(2..=3),
+                height: 1,
+            };
+            let color = match rng.gen_range(0..3) {
+                0 => CarpetColor::Green,
+                1 => CarpetColor::Red,
+                _ => CarpetColor::Violet,
+            };
+            // Carpets are walkable so they don't need to be added into `used`. The position is the
+            // top-left corner of the carpet.
+            Furniture::Carpet.build_carpet(
+                &mut commands,
+                furnitures_texture.clone(),
+                x + 5.,
+                y + 25.,
+                crate::game::InsideHouse,
+                dimension,
+                color,
+            );
+        }
+    }
+
+    for _ in 0..rng.gen_range(1..=INSIDE_MAX_RANDOM_FURNITURES) {
+        let furniture = INSIDE_RANDOM_FURNITURES[rng.gen_range(0..INSIDE_RANDOM_FURNITURES.len())];
+        let dim = furniture.pos_in_image();
+        if let Some(pos) = crate::map::find_free_spot(
+            &mut rng,
+            floor,
+            Vec2::new(dim.width(), dim.height()),
+            &mut used,
+        ) {
+            insert_furniture(
+                &mut commands,
+                furnitures_texture.clone(),
+                furniture,
+                pos.x,
+                // `insert_furniture` expects the center of the bottom half of the furniture.
+                pos.y - dim.height() / 4.,
+                crate::game::InsideHouse,
+                false,
+            );
+        }
     }
 }
 
-#[derive(Debug, Component, Clone, Copy)]
+#[derive(Debug, Component, Clone, Copy, Deserialize)]
 pub enum Statue {
     Magus = 0,
     Knight = 1,
@@ -783,7 +911,8 @@ impl Statue {
         texture: Handle<Image>,
         x: f32,
         y: f32,
-    ) {
+        chunk: Chunk,
+    ) -> Rect {
         let index = *self as usize;
         let (width, offset_x) = if index == Statue::Magus as usize {
             (54., -2.)
@@ -794,6 +923,7 @@ impl Statue {
             .spawn((
                 *self,
                 crate::game::OutsideWorld,
+                chunk,
                 TextureAtlas {
                     layout: atlas.clone(),
                     index: index * 2,
@@ -838,14 +968,30 @@ impl Statue {
                     TransformBundle::from(Transform::from_xyz(offset_x, -42., 0.)),
                 ));
             });
+        // The bottom part goes down to `-47 - (HEIGHT - 60) / 2`.
+        Rect::new(x - width / 2., y - 65., x + width / 2., y + 59. / 2.)
     }
 }
 
 pub fn spawn_statues(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlasLayout>,
+    chunk: Chunk,
+    objects: &[&MapObject],
+    obstacles: &mut Vec<Rect>,
 ) {
+    let statues = objects
+        .iter()
+        .filter_map(|object| match object.kind {
+            MapObjectKind::Statue(statue) => Some((statue, object.x, object.y)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if statues.is_empty() {
+        return;
+    }
+
     let statues_texture = asset_server.load("textures/statues.png");
     let mut statues_texture_atlas = TextureAtlasLayout::new_empty(UVec2::new(153, 96));
     // We split the top from the bottom part.
@@ -857,25 +1003,14 @@ pub fn spawn_statues(
     statues_texture_atlas.add_texture(URect::new(107, 60, 153, 96));
     let statues_texture_atlas_handle = texture_atlases.add(statues_texture_atlas);
 
-    Statue::Magus.create(
-        &mut commands,
-        statues_texture_atlas_handle.clone(),
-        statues_texture.clone(),
-        0.,
-        0.,
-    );
-    Statue::Knight.create(
-        &mut commands,
-        statues_texture_atlas_handle.clone(),
-        statues_texture.clone(),
-        70.,
-        0.,
-    );
-    Statue::Archer.create(
-        &mut commands,
-        statues_texture_atlas_handle.clone(),
-        statues_texture.clone(),
-        140.,
-        0.,
-    );
+    for (statue, x, y) in statues {
+        obstacles.push(statue.create(
+            commands,
+            statues_texture_atlas_handle.clone(),
+            statues_texture.clone(),
+            x,
+            y,
+            chunk,
+        ));
+    }
 }
@@ -1,12 +1,13 @@
 use crate::inventory::Inventory;
 use crate::stat::Stat;
-use crate::weapon::Weapon;
+use crate::weapon::{CharacterDied, Weapon};
 use crate::STAT_POINTS_PER_LEVEL;
 
 use bevy::ecs::component::Component;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::CollisionEvent;
 use bevy_rapier2d::rapier::geometry::CollisionEventFlags;
+use serde::Deserialize;
 
 use std::time::Duration;
 
@@ -19,7 +20,7 @@ pub struct GrassEffect {
 pub struct GrassEffectBundle;
 
 impl GrassEffectBundle {
-    pub fn new(parent_height: f32, asset_server: Res<AssetServer>) -> (GrassEffect, SpriteBundle) {
+    pub fn new(parent_height: f32, asset_server: &AssetServer) -> (GrassEffect, SpriteBundle) {
         (
             GrassEffect { count: 0 },
             SpriteBundle {
@@ -79,7 +80,7 @@ pub struct CharacterStats {
     pub attack: u32,
     /// The higher it is, the less time it takes to perform an attack.
     pub attack_speed: f32,
-    // FIXME: this isn't used at the moment.
+    /// Used by spells.
     pub magical_attack: u32,
     pub magical_defense: u32,
     /// It also takes into account the opponent level, agility and dexterity.
@@ -90,7 +91,7 @@ pub struct CharacterStats {
     pub move_speed: f32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Deserialize)]
 pub struct CharacterPoints {
     pub strength: u32,
     pub constitution: u32,
@@ -188,6 +189,22 @@ pub struct Character {
     pub width: f32,
     pub height: f32,
     pub attack_timer: Timer,
+    /// While it's running, the character can't be hit.
+    pub invulnerability_timer: Timer,
+}
+
+/// Roughly how many monsters of the same level a character needs to kill to level up.
+const KILLS_PER_LEVEL: u64 = 6;
+/// Time (in seconds) during which a character can't be hit again after taking a hit.
+const INVULNERABILITY_TIME: f32 = 0.5;
+const LEVEL_UP_NB_FRAMES: usize = 8;
+const LEVEL_UP_FRAME_TIME: f32 = 0.08;
+
+/// Sent every time a character gains a level.
+#[derive(Debug, Event)]
+pub struct LevelUp {
+    pub entity: Entity,
+    pub level: u16,
 }
 
 fn compute_xp_to_next_level(level: u16) -> u64 {
@@ -217,6 +234,9 @@ impl Character {
     ) -> Self {
         let stats = points.generate_stats(level);
         let unassigned = points.assigned_points();
+        let mut invulnerability_timer = Timer::from_seconds(INVULNERABILITY_TIME, TimerMode::Once);
+        // Characters can be hit right away.
+        invulnerability_timer.tick(invulnerability_timer.duration());
         Self {
             xp_to_next_level: compute_xp_to_next_level(level),
             xp,
@@ -229,20 +249,32 @@ impl Character {
             height,
             kind,
             attack_timer: Timer::new(Duration::from_secs(0), TimerMode::Once),
+            invulnerability_timer,
         }
     }
 
-    pub fn increase_xp(&mut self, xp_to_add: u64) {
+    pub fn is_invulnerable(&self) -> bool {
+        !self.invulnerability_timer.finished()
+    }
+
+    /// Returns `true` if the character gained at least one level.
+    pub fn increase_xp(&mut self, xp_to_add: u64) -> bool {
+        let level = self.level;
         self.xp += xp_to_add;
-        if self.xp >= self.xp_to_next_level {
+        while self.xp >= self.xp_to_next_level {
             self.level += 1;
             self.xp -= self.xp_to_next_level;
             self.xp_to_next_level = self.xp_to_next_level + self.xp_to_next_level / 2;
-            self.reset_stats();
             self.stats = self.points.generate_stats(self.level);
+            self.reset_stats();
             self.unused_points += STAT_POINTS_PER_LEVEL;
-            // self.animations.push(Animation::new_level_up(textures));
         }
+        level != self.level
+    }
+
+    /// How much XP killing this character gives.
+    pub fn xp_reward(&self) -> u64 {
+        compute_xp_to_next_level(self.level) / KILLS_PER_LEVEL
     }
 
     pub fn use_stat_point(&mut self) {
@@ -258,12 +290,10 @@ impl Character {
         self.stats.stamina.reset();
     }
 
-    pub fn set_weapon(
-        &mut self,
-        // For now, weapon is not used, but then, the weight will need to be taken into account.
-        weapon: &Weapon,
-    ) {
+    pub fn set_weapon(&mut self, weapon: &Weapon) {
         let mut time_for_an_attack_in_millis = 333. - self.stats.attack_speed / 10.;
+        // A weapon weighing 1 doesn't change anything, heavier ones are slower to swing.
+        time_for_an_attack_in_millis *= 0.5 + weapon.weight / 2.;
         if time_for_an_attack_in_millis < 50. {
             time_for_an_attack_in_millis = 50.;
         }
@@ -334,6 +364,110 @@ impl CharacterAnimationInfo {
     }
 }
 
+pub fn give_xp(
+    entity: Entity,
+    character: &mut Character,
+    xp: u64,
+    level_ups: &mut EventWriter<LevelUp>,
+) {
+    if character.increase_xp(xp) {
+        level_ups.send(LevelUp {
+            entity,
+            level: character.level,
+        });
+    }
+}
+
+/// Gives XP to the characters which killed another one.
+pub fn award_kill_xp(
+    mut deaths: EventReader<CharacterDied>,
+    mut characters: Query<&mut Character>,
+    mut level_ups: EventWriter<LevelUp>,
+) {
+    // A character can be killed by more than one attack at once.
+    let mut rewarded = Vec::new();
+    for death in deaths.read() {
+        let killer = match death.killer {
+            Some(killer) if !rewarded.contains(&death.entity) => killer,
+            _ => continue,
+        };
+        let xp = match characters.get(death.entity) {
+            Ok(dead) => dead.xp_reward(),
+            Err(_) => continue,
+        };
+        rewarded.push(death.entity);
+        if let Ok(mut character) = characters.get_mut(killer) {
+            give_xp(killer, &mut character, xp, &mut level_ups);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct LevelUpAnimation {
+    timer: Timer,
+}
+
+pub fn spawn_level_up_animation(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    mut events: EventReader<LevelUp>,
+    characters: Query<&Character>,
+) {
+    for event in events.read() {
+        let character = match characters.get(event.entity) {
+            Ok(character) => character,
+            Err(_) => continue,
+        };
+        let texture_atlas = TextureAtlasLayout::from_grid(
+            UVec2::new(82, 35),
+            LEVEL_UP_NB_FRAMES as _,
+            1,
+            None,
+            None,
+        );
+        let width = character.width * 2.;
+        let height = width * 35. / 82.;
+        let child = commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(width, height)),
+                        ..default()
+                    },
+                    texture: asset_server.load("textures/level-up.png"),
+                    transform: Transform::from_xyz(0., (height - character.height) / 2., 0.5),
+                    ..default()
+                },
+                TextureAtlas {
+                    layout: texture_atlases.add(texture_atlas),
+                    index: 0,
+                },
+                LevelUpAnimation {
+                    timer: Timer::from_seconds(LEVEL_UP_FRAME_TIME, TimerMode::Repeating),
+                },
+            ))
+            .id();
+        commands.entity(event.entity).add_child(child);
+    }
+}
+
+pub fn animate_level_up(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut animations: Query<(Entity, &mut LevelUpAnimation, &mut TextureAtlas)>,
+) {
+    for (entity, mut animation, mut sprite) in animations.iter_mut() {
+        if animation.timer.tick(time.delta()).just_finished() {
+            if sprite.index + 1 >= LEVEL_UP_NB_FRAMES {
+                commands.entity(entity).despawn_recursive();
+            } else {
+                sprite.index += 1;
+            }
+        }
+    }
+}
+
 pub fn animate_character_system(
     time: Res<Time>,
     mut animation_query: Query<(&mut CharacterAnimationInfo, &mut TextureAtlas)>,
@@ -412,6 +546,16 @@ impl CharacterAnimationType {
         }
     }
 
+    /// The direction the character is facing.
+    pub fn direction(self) -> Vec2 {
+        match self {
+            Self::ForwardMove | Self::ForwardIdle => Vec2::NEG_Y,
+            Self::BackwardMove | Self::BackwardIdle => Vec2::Y,
+            Self::LeftMove | Self::LeftIdle => Vec2::NEG_X,
+            Self::RightMove | Self::RightIdle => Vec2::X,
+        }
+    }
+
     pub fn set_move(&mut self, x_axis: i8, y_axis: i8) {
         if x_axis < 0 {
             *self = Self::LeftMove;
@@ -425,14 +569,24 @@ impl CharacterAnimationType {
     }
 }
 
-pub fn refresh_characters_stats(timer: Res<Time>, mut characters: Query<&mut Character>) {
+pub fn refresh_characters_stats(
+    timer: Res<Time>,
+    mut characters: Query<
+        (&mut Character, Option<&crate::status_effect::StatusEffects>),
+        Without<crate::death::Dying>,
+    >,
+) {
     let delta = timer.delta().as_secs_f32();
-    for mut character in characters.iter_mut() {
+    for (mut character, effects) in characters.iter_mut() {
+        character.invulnerability_timer.tick(timer.delta());
         // stamina doesn't regen when attacking.
         if !character.is_attacking {
             character.stats.stamina.refresh(delta);
         }
-        character.stats.health.refresh(delta);
+        // health doesn't regen when poisoned or burning.
+        if !effects.map_or(false, |effects| effects.pauses_regeneration()) {
+            character.stats.health.refresh(delta);
+        }
         character.stats.mana.refresh(delta);
     }
 }
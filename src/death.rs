@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier2d::prelude::*;
+
+use crate::building::Statue;
+use crate::character::Character;
+use crate::game::{GameState, OutsideWorld};
+use crate::inventory::Inventory;
+use crate::player::Player;
+use crate::status_effect::StatusEffects;
+use crate::weapon::CharacterDied;
+
+const DEATH_NB_FRAMES: usize = 15;
+const DEATH_FRAME_TIME: f32 = 0.06;
+/// If the player comes closer than this to a statue, it becomes their respawn point.
+const STATUE_VISIT_DISTANCE: f32 = 80.;
+/// Where the player respawns compared to the statue position, so they're not stuck inside it.
+const STATUE_RESPAWN_OFFSET: f32 = -90.;
+
+/// Added on a character whose health reached zero. Once the death animation is over, monsters
+/// are despawned and the player gets the death screen.
+#[derive(Debug, Component)]
+pub struct Dying {
+    timer: Timer,
+}
+
+#[derive(Component)]
+pub struct DeathAnimation {
+    timer: Timer,
+}
+
+/// Where the player goes back to after dying: the last statue they visited.
+#[derive(Debug, Resource)]
+pub struct RespawnPoint(pub Vec2);
+
+impl Default for RespawnPoint {
+    fn default() -> Self {
+        // Where the player starts.
+        Self(Vec2::new(0., 210.))
+    }
+}
+
+/// What the player loses when dying, in percent of what they have.
+#[derive(Debug, Resource)]
+pub struct DeathPenalty {
+    pub gold_percent: u32,
+    /// Only the XP of the current level is affected, the player cannot lose a level.
+    pub xp_percent: u32,
+}
+
+impl Default for DeathPenalty {
+    fn default() -> Self {
+        Self {
+            gold_percent: 10,
+            xp_percent: 0,
+        }
+    }
+}
+
+/// Starts the death of the characters whose health reached zero.
+pub fn handle_character_deaths(mut commands: Commands, mut deaths: EventReader<CharacterDied>) {
+    for death in deaths.read() {
+        kill(&mut commands, death.entity);
+    }
+}
+
+/// Starts the death of `entity`. It'll be handled by the other systems of this module.
+pub fn kill(commands: &mut Commands, entity: Entity) {
+    commands.entity(entity).insert((
+        Dying {
+            timer: Timer::from_seconds(DEATH_NB_FRAMES as f32 * DEATH_FRAME_TIME, TimerMode::Once),
+        },
+        RigidBodyDisabled,
+    ));
+}
+
+/// Hides the dying characters, disables their colliders and plays the death animation where they
+/// were.
+pub fn start_dying(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    mut dying: Query<
+        (
+            &Character,
+            &Transform,
+            &Children,
+            &mut Velocity,
+            &mut Visibility,
+        ),
+        Added<Dying>,
+    >,
+    colliders: Query<Entity, With<Collider>>,
+) {
+    for (character, transform, children, mut velocity, mut visibility) in dying.iter_mut() {
+        *velocity = Velocity::zero();
+        *visibility = Visibility::Hidden;
+        for child in children.iter() {
+            if colliders.contains(*child) {
+                commands.entity(*child).insert(ColliderDisabled);
+            }
+        }
+
+        let texture_atlas =
+            TextureAtlasLayout::from_grid(UVec2::new(22, 22), DEATH_NB_FRAMES as _, 1, None, None);
+        let size = character.width.max(character.height);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                texture: asset_server.load("textures/death.png"),
+                transform: *transform,
+                ..default()
+            },
+            TextureAtlas {
+                layout: texture_atlases.add(texture_atlas),
+                index: 0,
+            },
+            DeathAnimation {
+                timer: Timer::from_seconds(DEATH_FRAME_TIME, TimerMode::Repeating),
+            },
+        ));
+    }
+}
+
+pub fn animate_death(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut animations: Query<(Entity, &mut DeathAnimation, &mut TextureAtlas)>,
+) {
+    for (entity, mut animation, mut sprite) in animations.iter_mut() {
+        if animation.timer.tick(time.delta()).just_finished() {
+            if sprite.index + 1 >= DEATH_NB_FRAMES {
+                commands.entity(entity).despawn_recursive();
+            } else {
+                sprite.index += 1;
+            }
+        }
+    }
+}
+
+/// Despawns the monsters once their death animation is over. The player stays around until they
+/// respawn.
+pub fn update_dying(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut dying: Query<(Entity, &mut Dying), Without<Player>>,
+) {
+    for (entity, mut dying) in dying.iter_mut() {
+        if dying.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn update_respawn_point(
+    mut respawn_point: ResMut<RespawnPoint>,
+    player: Query<&Transform, (With<Player>, Changed<Transform>)>,
+    statues: Query<&Transform, (With<Statue>, With<OutsideWorld>)>,
+) {
+    let player = match player.get_single() {
+        Ok(p) => p.translation.truncate(),
+        _ => return,
+    };
+    for statue in statues.iter() {
+        let pos = statue.translation.truncate();
+        if pos.distance(player) < STATUE_VISIT_DISTANCE {
+            respawn_point.0 = pos + Vec2::new(0., STATUE_RESPAWN_OFFSET);
+            return;
+        }
+    }
+}
+
+pub fn show_death_screen(
+    mut commands: Commands,
+    mut egui_context: EguiContexts,
+    time: Res<Time>,
+    penalty: Res<DeathPenalty>,
+    respawn_point: Res<RespawnPoint>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut player: Query<(
+        Entity,
+        &mut Dying,
+        &mut Player,
+        &mut Character,
+        &mut Inventory,
+        &mut Transform,
+        &mut Visibility,
+        &Children,
+    )>,
+) {
+    let (
+        entity,
+        mut dying,
+        mut player,
+        mut character,
+        mut inventory,
+        mut transform,
+        mut visibility,
+        children,
+    ) = match player.get_single_mut() {
+        Ok(p) => p,
+        _ => return,
+    };
+    if !dying.timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    let lost_gold = inventory.gold * penalty.gold_percent / 100;
+    let lost_xp = character.xp * penalty.xp_percent as u64 / 100;
+    let mut respawn = false;
+    egui::Window::new("You died")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                if lost_gold > 0 {
+                    ui.label(&format!("You lost {} gold.", lost_gold));
+                }
+                if lost_xp > 0 {
+                    ui.label(&format!("You lost {} XP.", lost_xp));
+                }
+                respawn = ui.button("Respawn").clicked();
+            });
+        });
+    if !respawn {
+        return;
+    }
+
+    inventory.gold -= lost_gold;
+    character.xp -= lost_xp;
+    character.reset_stats();
+    *visibility = Visibility::Inherited;
+    commands
+        .entity(entity)
+        .remove::<(Dying, RigidBodyDisabled, StatusEffects)>();
+    for child in children.iter() {
+        commands.entity(*child).remove::<ColliderDisabled>();
+    }
+    if *game_state == GameState::Outside {
+        transform.translation.x = respawn_point.0.x;
+        transform.translation.y = respawn_point.0.y;
+    } else {
+        // The player position is restored from there when going back outside.
+        player.old_x = respawn_point.0.x;
+        player.old_y = respawn_point.0.y;
+        next_game_state.set(GameState::Outside);
+    }
+}
@@ -0,0 +1,420 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::draw;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use rand_seeder::Seeder;
+
+use crate::building::{insert_furniture, EnterArea, Furniture};
+use crate::game::InsideDungeon;
+use crate::inventory::{Consumable, InventoryItem};
+use crate::loot::{LootDrop, LootEntry, LootTable};
+use crate::map::{find_free_spot, Chunk};
+use crate::monster::{MonsterKind, MonsterRegistry, MONSTER_SIZE};
+use crate::status_effect::StatusEffect;
+use crate::weapon::Weapon;
+
+const TILE_SIZE: f32 = 32.;
+/// The dungeon is generated on a grid of `GRID_SIZE` x `GRID_SIZE` tiles.
+const GRID_SIZE: i32 = 48;
+const MIN_ROOMS: usize = 5;
+const MAX_ROOMS: usize = 9;
+const MIN_ROOM_SIZE: i32 = 6;
+const MAX_ROOM_SIZE: i32 = 11;
+/// How many times we try to place a room before giving up.
+const MAX_ROOM_ATTEMPTS: usize = 200;
+const CORRIDOR_WIDTH: i32 = 2;
+const MAX_MONSTERS_PER_ROOM: usize = 3;
+/// Chance (in percent) for a room to contain a chest.
+const CHEST_CHANCE: u8 = 40;
+const MONSTER_LEVEL: u16 = 3;
+
+const ENTRANCE_WIDTH: f32 = 48.;
+const ENTRANCE_HEIGHT: f32 = 40.;
+/// Space taken by an entrance on the map. We keep some room in front of it so it can be reached.
+pub const ENTRANCE_FOOTPRINT: Vec2 = Vec2::new(ENTRANCE_WIDTH, ENTRANCE_HEIGHT * 3.);
+
+const WALL_COLOR: Color = Color::LinearRgba(LinearRgba::rgb(0.2, 0.18, 0.18));
+const FLOOR_COLOR: Color = Color::LinearRgba(LinearRgba::rgb(0.38, 0.35, 0.31));
+const EXIT_COLOR: Color = Color::LinearRgba(LinearRgba::rgb(0.7, 0.65, 0.5));
+
+#[derive(Debug, Component, Clone, Copy)]
+pub struct DungeonEntrance {
+    /// Used to generate the dungeon.
+    pub hash: u32,
+}
+
+#[derive(Debug, Component)]
+pub struct Chest;
+
+fn chest_loot() -> LootTable {
+    LootTable {
+        rolls: 3,
+        entries: vec![
+            LootEntry {
+                weight: 50,
+                drop: Some(LootDrop::Gold { min: 5, max: 20 }),
+            },
+            LootEntry {
+                weight: 35,
+                drop: Some(LootDrop::Item(InventoryItem::Collectible { quantity: 1 })),
+            },
+            LootEntry {
+                weight: 15,
+                drop: Some(LootDrop::Item(InventoryItem::Weapon(Weapon::sword(3)))),
+            },
+            LootEntry {
+                weight: 5,
+                drop: Some(LootDrop::Item(InventoryItem::Weapon(Weapon::dagger(2)))),
+            },
+            LootEntry {
+                weight: 5,
+                drop: Some(LootDrop::Item(InventoryItem::Weapon(Weapon::axe(5)))),
+            },
+            LootEntry {
+                weight: 5,
+                drop: Some(LootDrop::Item(InventoryItem::Weapon(Weapon::spear(4)))),
+            },
+            LootEntry {
+                weight: 5,
+                drop: Some(LootDrop::Item(InventoryItem::Weapon(Weapon::bow(2)))),
+            },
+            LootEntry {
+                weight: 5,
+                drop: Some(LootDrop::Item(InventoryItem::Weapon(Weapon {
+                    effect: Some(StatusEffect::poison(2., 5.)),
+                    ..Weapon::dagger(2)
+                }))),
+            },
+            LootEntry {
+                weight: 5,
+                drop: Some(LootDrop::Item(InventoryItem::Weapon(Weapon {
+                    effect: Some(StatusEffect::slow(0.5, 3.)),
+                    ..Weapon::spear(3)
+                }))),
+            },
+            LootEntry {
+                weight: 3,
+                drop: Some(LootDrop::Item(InventoryItem::Weapon(Weapon {
+                    effect: Some(StatusEffect::stun(0.8)),
+                    ..Weapon::axe(4)
+                }))),
+            },
+            LootEntry {
+                weight: 15,
+                drop: Some(LootDrop::Item(InventoryItem::Consumable(
+                    Consumable::healing_potion(),
+                ))),
+            },
+            LootEntry {
+                weight: 10,
+                drop: Some(LootDrop::Item(InventoryItem::Consumable(
+                    Consumable::antidote(),
+                ))),
+            },
+            LootEntry {
+                weight: 5,
+                drop: Some(LootDrop::Item(InventoryItem::Weapon(
+                    Weapon::throwing_knives(1),
+                ))),
+            },
+        ],
+    }
+}
+
+pub fn insert_dungeon_entrance(commands: &mut Commands, x: f32, y: f32, chunk: Chunk) -> Rect {
+    let frame = shapes::Rectangle {
+        extents: Vec2::new(ENTRANCE_WIDTH, ENTRANCE_HEIGHT),
+        ..default()
+    };
+    let hole = shapes::Rectangle {
+        extents: Vec2::new(ENTRANCE_WIDTH / 2., ENTRANCE_HEIGHT / 2.),
+        ..default()
+    };
+
+    commands
+        .spawn((
+            DungeonEntrance {
+                hash: crate::map::hash_pos(x, y),
+            },
+            crate::game::OutsideWorld,
+            chunk,
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&frame),
+                spatial: SpatialBundle {
+                    transform: Transform::from_xyz(x, y, crate::FURNITURE_Z_INDEX),
+                    ..default()
+                },
+                ..default()
+            },
+            draw::Fill::color(WALL_COLOR),
+            RigidBody::Fixed,
+        ))
+        .with_children(|children| {
+            // The hole.
+            children.spawn((
+                ShapeBundle {
+                    path: GeometryBuilder::build_as(&hole),
+                    spatial: SpatialBundle {
+                        transform: Transform::from_xyz(0., ENTRANCE_HEIGHT / -4., 0.1),
+                        ..default()
+                    },
+                    ..default()
+                },
+                draw::Fill::color(Color::LinearRgba(LinearRgba::BLACK)),
+            ));
+            // The rock above the hole.
+            children.spawn((
+                Collider::cuboid(ENTRANCE_WIDTH / 2., ENTRANCE_HEIGHT / 4.),
+                CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
+                TransformBundle::from(Transform::from_xyz(0.0, ENTRANCE_HEIGHT / 4., 0.0)),
+            ));
+            // The "enter area" sensor.
+            children.spawn((
+                Collider::cuboid(ENTRANCE_WIDTH / 4., 4.),
+                EnterArea,
+                CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
+                Sensor,
+                TransformBundle::from(Transform::from_xyz(0.0, ENTRANCE_HEIGHT / -4., 0.0)),
+            ));
+        });
+
+    Rect::from_center_size(Vec2::new(x, y), ENTRANCE_FOOTPRINT)
+}
+
+struct Grid {
+    floor: Vec<bool>,
+}
+
+impl Grid {
+    fn new() -> Self {
+        Self {
+            floor: vec![false; (GRID_SIZE * GRID_SIZE) as usize],
+        }
+    }
+
+    fn is_floor(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && y >= 0
+            && x < GRID_SIZE
+            && y < GRID_SIZE
+            && self.floor[(y * GRID_SIZE + x) as usize]
+    }
+
+    fn is_wall(&self, x: i32, y: i32) -> bool {
+        if self.is_floor(x, y) {
+            return false;
+        }
+        (-1..=1).any(|dy| (-1..=1).any(|dx| self.is_floor(x + dx, y + dy)))
+    }
+
+    fn carve(&mut self, area: IRect) {
+        for y in area.min.y..area.max.y {
+            for x in area.min.x..area.max.x {
+                self.floor[(y * GRID_SIZE + x) as usize] = true;
+            }
+        }
+    }
+
+    fn carve_corridor(&mut self, from: IVec2, to: IVec2, horizontal_first: bool) {
+        let corner = if horizontal_first {
+            IVec2::new(to.x, from.y)
+        } else {
+            IVec2::new(from.x, to.y)
+        };
+        for (start, end) in [(from, corner), (corner, to)] {
+            let min = start.min(end);
+            let max = start.max(end);
+            self.carve(IRect::new(
+                min.x,
+                min.y,
+                max.x + CORRIDOR_WIDTH,
+                max.y + CORRIDOR_WIDTH,
+            ));
+        }
+    }
+
+    /// Returns the horizontal runs of tiles matching `filter`, as `(y, start_x, end_x)`.
+    fn runs<F: Fn(i32, i32) -> bool>(&self, filter: F) -> Vec<(i32, i32, i32)> {
+        let mut runs = Vec::new();
+        for y in 0..GRID_SIZE {
+            let mut start = None;
+            for x in 0..=GRID_SIZE {
+                match (start, x < GRID_SIZE && filter(x, y)) {
+                    (None, true) => start = Some(x),
+                    (Some(start_x), false) => {
+                        runs.push((y, start_x, x));
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        runs
+    }
+}
+
+fn generate_rooms(rng: &mut crate::SeedType) -> Vec<IRect> {
+    let nb_rooms = rng.gen_range(MIN_ROOMS..=MAX_ROOMS);
+    let mut rooms: Vec<IRect> = Vec::with_capacity(nb_rooms);
+
+    for _ in 0..MAX_ROOM_ATTEMPTS {
+        if rooms.len() >= nb_rooms {
+            break;
+        }
+        let width = rng.gen_range(MIN_ROOM_SIZE..=MAX_ROOM_SIZE);
+        let height = rng.gen_range(MIN_ROOM_SIZE..=MAX_ROOM_SIZE);
+        // We keep the grid borders free for the walls.
+        let x = rng.gen_range(1..GRID_SIZE - width - 1);
+        let y = rng.gen_range(1..GRID_SIZE - height - 1);
+        let room = IRect::new(x, y, x + width, y + height);
+        // Rooms are kept apart so there is always a wall between them.
+        if rooms
+            .iter()
+            .all(|other| other.inflate(2).intersect(room).is_empty())
+        {
+            rooms.push(room);
+        }
+    }
+    rooms
+}
+
+fn tile_pos(x: f32, y: f32) -> Vec2 {
+    Vec2::new(x * TILE_SIZE, y * TILE_SIZE)
+}
+
+fn spawn_runs(
+    children: &mut ChildBuilder,
+    runs: Vec<(i32, i32, i32)>,
+    color: Color,
+    with_collider: bool,
+) {
+    for (y, start_x, end_x) in runs {
+        let len = (end_x - start_x) as f32;
+        // Center of the run.
+        let pos = tile_pos(start_x as f32 + (len - 1.) / 2., y as f32);
+        let mut entity = children.spawn(SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::new(len * TILE_SIZE, TILE_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_xyz(pos.x, pos.y, 0.),
+            ..default()
+        });
+        if with_collider {
+            entity.insert((
+                Collider::cuboid(len * TILE_SIZE / 2., TILE_SIZE / 2.),
+                CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
+            ));
+        }
+    }
+}
+
+pub fn spawn_dungeon(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    app_state: Res<crate::GameInfo>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    monsters: Res<MonsterRegistry>,
+) {
+    let mut rng: crate::SeedType = Seeder::from(&format!(
+        "{};dungeon;{}",
+        crate::SEED,
+        app_state.dungeon_hash
+    ))
+    .make_rng();
+
+    let rooms = generate_rooms(&mut rng);
+    let mut grid = Grid::new();
+    for room in &rooms {
+        grid.carve(*room);
+    }
+    for pair in rooms.windows(2) {
+        let horizontal_first = rng.gen();
+        grid.carve_corridor(pair[0].center(), pair[1].center(), horizontal_first);
+    }
+
+    // The player arrives in the first room, at the position computed when entering.
+    let first_room = rooms[0];
+    let origin = app_state.pos - tile_pos(first_room.center().x as _, first_room.center().y as _);
+    let exit_pos = tile_pos(first_room.center().x as _, (first_room.max.y - 1) as _);
+
+    commands
+        .spawn((
+            InsideDungeon,
+            SpatialBundle::from_transform(Transform::from_xyz(
+                origin.x,
+                origin.y,
+                crate::BACKGROUND_Z_INDEX,
+            )),
+            RigidBody::Fixed,
+        ))
+        .with_children(|children| {
+            spawn_runs(
+                children,
+                grid.runs(|x, y| grid.is_floor(x, y)),
+                FLOOR_COLOR,
+                false,
+            );
+            spawn_runs(
+                children,
+                grid.runs(|x, y| grid.is_wall(x, y)),
+                WALL_COLOR,
+                true,
+            );
+            // The exit.
+            children.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: EXIT_COLOR,
+                        custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE / 2.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(exit_pos.x, exit_pos.y, crate::CARPET_Z_INDEX),
+                    ..default()
+                },
+                Collider::cuboid(TILE_SIZE / 4., 4.),
+                CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
+                Sensor,
+                EnterArea,
+            ));
+        });
+
+    let furnitures_texture = asset_server.load("textures/furnitures.png");
+    let mut used = Vec::new();
+    for room in rooms.iter().skip(1) {
+        let min = origin + tile_pos(room.min.x as _, room.min.y as _) - TILE_SIZE / 2.;
+        let max = origin + tile_pos(room.max.x as _, room.max.y as _) - TILE_SIZE / 2.;
+        let area = Rect::from_corners(min, max);
+
+        for _ in 0..rng.gen_range(1..=MAX_MONSTERS_PER_ROOM) {
+            if let Some(pos) = find_free_spot(&mut rng, area, Vec2::splat(MONSTER_SIZE), &mut used)
+            {
+                MonsterKind::Skeleton.spawn(
+                    &mut commands,
+                    &asset_server,
+                    &mut texture_atlases,
+                    &monsters,
+                    pos.x,
+                    pos.y,
+                    MONSTER_LEVEL,
+                    InsideDungeon,
+                );
+            }
+        }
+        if rng.gen_range(0..100) < CHEST_CHANCE {
+            if let Some(pos) = find_free_spot(&mut rng, area, Vec2::splat(TILE_SIZE), &mut used) {
+                insert_furniture(
+                    &mut commands,
+                    furnitures_texture.clone(),
+                    Furniture::Crate,
+                    pos.x,
+                    pos.y,
+                    (InsideDungeon, Chest, chest_loot()),
+                    false,
+                );
+            }
+        }
+    }
+}
@@ -1,21 +1,28 @@
 use bevy::ecs::system::EntityCommands;
 use bevy::math::Rect;
 use bevy::prelude::*;
+use bevy::utils::Duration;
 use bevy_rapier2d::prelude::*;
 use bevy_rapier2d::rapier::geometry::CollisionEventFlags;
 use rand::Rng;
-use rand_seeder::Seeder;
+use serde::Deserialize;
 
+use crate::biome::Biome;
 use crate::character::{Character, GrassEffect};
+use crate::map::{find_free_spot, Chunk};
+use crate::map_file::{MapObject, MapObjectKind};
+use crate::weapon::{EntityDestroyer, GrassCut};
+use crate::world_delta::{Change, WorldDelta};
 
 #[derive(Debug, Component)]
 pub struct Bush;
 
-fn insert_bush(texture: Handle<Image>, commands: &mut Commands, x: f32, y: f32) {
+fn insert_bush(texture: Handle<Image>, commands: &mut Commands, x: f32, y: f32, chunk: Chunk) {
     commands
         .spawn((
             Bush,
             crate::game::OutsideWorld,
+            chunk,
             SpriteBundle {
                 texture,
                 transform: Transform::from_xyz(x, y, 0.0),
@@ -31,9 +38,9 @@ fn insert_bush(texture: Handle<Image>, commands: &mut Commands, x: f32, y: f32)
         });
 }
 
-#[derive(Debug, Component, Clone, Copy)]
+#[derive(Debug, Component, Clone, Copy, Deserialize)]
 #[repr(usize)]
-enum Tree {
+pub enum Tree {
     Normal = 0,
     Sapin = 1,
     Scary = 2,
@@ -97,11 +104,13 @@ fn insert_tree(
     tree: Tree,
     x: f32,
     y: f32,
+    chunk: Chunk,
 ) {
     commands
         .spawn((
             tree,
             crate::game::OutsideWorld,
+            chunk,
             TextureAtlas {
                 index: tree as _,
                 layout,
@@ -130,16 +139,18 @@ fn insert_grass(
     y: f32,
     max_row: usize,
     max_col: usize,
+    density: u8,
+    chunk: Chunk,
+    rng: &mut crate::SeedType,
+    cut_grass_texture: Handle<Image>,
+    delta: &WorldDelta,
 ) {
     // first we generate the "grid" of grass.
-    let mut rng: crate::SeedType =
-        Seeder::from(&format!("{};{:.1};{:.1}", crate::SEED, x, y)).make_rng();
     let mut v = Vec::with_capacity(max_row);
     for _ in 0..max_row {
         let mut line = Vec::with_capacity(max_col);
         for _ in 0..max_col {
-            // 70% of chance to have grass.
-            line.push((rng.gen::<u8>() % 10) > 2);
+            line.push(rng.gen_range(0..100) < density);
         }
         v.push(line);
     }
@@ -154,119 +165,390 @@ fn insert_grass(
             let has_grass_below = v.get(row + 1).map(|c| c[pos]).unwrap_or(false);
             let index = if has_grass_below { nb % 5 } else { nb % 5 + 5 };
             nb += 1;
-            commands.spawn((
-                Grass,
-                crate::game::OutsideWorld,
-                TextureAtlas {
-                    index,
-                    layout: layout.clone(),
-                },
-                SpriteBundle {
-                    sprite: Sprite {
-                        custom_size: Some(Vec2 {
-                            x: crate::GRASS_SIZE,
-                            y: crate::GRASS_SIZE,
-                        }),
-                        ..default()
-                    },
-                    texture: texture.clone(),
-                    transform: Transform::from_xyz(
-                        x + pos as f32 * crate::GRASS_SIZE,
-                        y - row as f32 * crate::GRASS_SIZE,
-                        0.0,
-                    ),
-                    ..default()
-                },
-                Sensor,
-                Collider::cuboid(crate::GRASS_SIZE / 2., crate::GRASS_SIZE / 2.),
-                CollisionGroups::new(
-                    crate::OUTSIDE_WORLD | crate::HITBOX,
-                    crate::OUTSIDE_WORLD | crate::HITBOX,
-                ),
-            ));
+            let grass_x = x + pos as f32 * crate::GRASS_SIZE;
+            let grass_y = y - row as f32 * crate::GRASS_SIZE;
+            let atlas = TextureAtlas {
+                index,
+                layout: layout.clone(),
+            };
+            let transform = Transform::from_xyz(grass_x, grass_y, 0.0);
+            match delta.get(chunk, transform.translation.truncate()) {
+                Some(Change::GrassCut { regrow_at }) => {
+                    insert_cut_grass(
+                        commands,
+                        cut_grass_texture.clone(),
+                        transform,
+                        atlas,
+                        regrow_at,
+                        chunk,
+                    );
+                }
+                None => insert_grass_cell(commands, texture.clone(), transform, atlas, chunk),
+            }
         }
     }
 }
 
-pub fn spawn_nature(
+fn insert_grass_cell(
+    commands: &mut Commands,
+    texture: Handle<Image>,
+    transform: Transform,
+    atlas: TextureAtlas,
+    chunk: Chunk,
+) {
+    commands.spawn((
+        Grass,
+        crate::game::OutsideWorld,
+        chunk,
+        atlas,
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2 {
+                    x: crate::GRASS_SIZE,
+                    y: crate::GRASS_SIZE,
+                }),
+                ..default()
+            },
+            texture,
+            transform,
+            ..default()
+        },
+        Sensor,
+        Collider::cuboid(crate::GRASS_SIZE / 2., crate::GRASS_SIZE / 2.),
+        CollisionGroups::new(
+            crate::OUTSIDE_WORLD | crate::HITBOX,
+            crate::OUTSIDE_WORLD | crate::HITBOX,
+        ),
+    ));
+}
+
+/// Grass which was cut and which will grow back.
+#[derive(Debug, Component)]
+pub struct CutGrass {
+    /// The atlas of the grass before it was cut, so it looks the same once it grew back.
+    atlas: TextureAtlas,
+    /// When the grass will grow back, compared to `Time::elapsed`.
+    regrow_at: Duration,
+}
+
+/// `atlas` is the one of the grass before it was cut.
+pub fn insert_cut_grass(
+    commands: &mut Commands,
+    texture: Handle<Image>,
+    transform: Transform,
+    atlas: TextureAtlas,
+    regrow_at: Duration,
+    chunk: Chunk,
+) {
+    commands.spawn((
+        CutGrass { atlas, regrow_at },
+        crate::game::OutsideWorld,
+        chunk,
+        SpriteBundle {
+            texture,
+            transform,
+            sprite: Sprite {
+                custom_size: Some(Vec2 {
+                    x: crate::GRASS_SIZE * 3. / 4.,
+                    y: crate::GRASS_SIZE * 3. / 4.,
+                }),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+/// Replaces the grass hit by an attack with cut grass, which will grow back later.
+pub fn cut_grass(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    time: Res<Time>,
+    grass_growth: Res<GrassGrowth>,
+    mut world_delta: ResMut<WorldDelta>,
+    mut grass_cuts: EventReader<GrassCut>,
+    mut grass: Query<(&Chunk, &TextureAtlas, &mut Transform), With<Grass>>,
 ) {
-    let bush_texture = asset_server.load("textures/bush.png");
+    // The same grass can be hit by more than one weapon at once.
+    let mut already_cut = Vec::new();
+    for grass_cut in grass_cuts.read() {
+        if already_cut.contains(&grass_cut.grass) {
+            continue;
+        }
+        let (chunk, atlas, mut transform) = match grass.get_mut(grass_cut.grass) {
+            Ok(grass) => grass,
+            Err(_) => continue,
+        };
+        already_cut.push(grass_cut.grass);
+        // We "remove" the existing grass...
+        commands.spawn(EntityDestroyer::new(grass_cut.grass, 0.1));
 
-    for nb in 0..8 {
-        insert_bush(
-            bush_texture.clone(),
+        // And replace it with a cut one which will grow back later.
+        let regrow_at = time.elapsed() + Duration::from_secs_f32(grass_growth.regrow_time);
+        insert_cut_grass(
             &mut commands,
-            nb as f32 * 40.,
-            (nb as f32) * 40. + 100.,
+            asset_server.load("textures/cut-grass.png"),
+            transform.clone(),
+            atlas.clone(),
+            regrow_at,
+            *chunk,
         );
+        // We keep track of it so it's still cut when the chunk is spawned again.
+        world_delta.record(
+            *chunk,
+            transform.translation.truncate(),
+            Change::GrassCut { regrow_at },
+        );
+        // Moving grass outside of view.
+        transform.translation.y -= crate::MAP_SIZE * 3.;
     }
+}
 
-    let trees_texture = asset_server.load("textures/trees.png");
-    let mut texture_atlas = TextureAtlasLayout::new_empty(UVec2::new(234, 71));
-    let trees = vec![
-        texture_atlas.add_texture(URect {
-            min: UVec2::new(0, 0),
-            max: UVec2::new(64, 64),
-        }),
-        texture_atlas.add_texture(URect {
-            min: UVec2::new(64, 0),
-            max: UVec2::new(128, 64),
-        }),
-        texture_atlas.add_texture(URect {
-            min: UVec2::new(128, 0),
-            max: UVec2::new(192, 71),
-        }),
-        texture_atlas.add_texture(URect {
-            min: UVec2::new(193, 0),
-            max: UVec2::new(234, 64),
-        }),
-    ];
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-    let mut x = -100.;
-    let mut y = 280.;
-    for nb in 0..6 {
-        if nb % 3 == 0 {
-            x = -100.;
-            y += 100.;
+pub fn regrow_grass(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut world_delta: ResMut<WorldDelta>,
+    cut_grass: Query<(Entity, &CutGrass, &Chunk, &Transform)>,
+) {
+    let now = time.elapsed();
+    for (entity, cut_grass, chunk, transform) in cut_grass.iter() {
+        if cut_grass.regrow_at > now {
+            continue;
         }
-        insert_tree(
-            &trees,
-            texture_atlas_handle.clone(),
-            trees_texture.clone(),
+        commands.entity(entity).despawn_recursive();
+        insert_grass_cell(
             &mut commands,
-            (nb % 4).into(),
+            asset_server.load("textures/grass.png"),
+            *transform,
+            cut_grass.atlas.clone(),
+            *chunk,
+        );
+        world_delta.forget(*chunk, transform.translation.truncate());
+    }
+}
+
+const TREE_SIZE: f32 = 64.;
+const BUSH_SIZE: f32 = 32.;
+/// Chance (in percent) for each cell of a hand-placed grass patch to actually have grass.
+const PLACED_GRASS_DENSITY: u8 = 70;
+/// How long (in seconds) it takes for cut grass to grow back by default.
+const GRASS_REGROW_TIME: f32 = 60.;
+
+#[derive(Debug, Resource)]
+pub struct GrassGrowth {
+    /// How long (in seconds) it takes for cut grass to grow back.
+    pub regrow_time: f32,
+}
+
+impl Default for GrassGrowth {
+    fn default() -> Self {
+        Self {
+            regrow_time: GRASS_REGROW_TIME,
+        }
+    }
+}
+
+/// Textures and atlas layouts needed to spawn nature elements.
+pub struct NatureTextures {
+    trees: Vec<usize>,
+    trees_layout: Handle<TextureAtlasLayout>,
+    trees_texture: Handle<Image>,
+    bush_texture: Handle<Image>,
+    grass_layout: Handle<TextureAtlasLayout>,
+    grass_texture: Handle<Image>,
+    cut_grass_texture: Handle<Image>,
+}
+
+impl NatureTextures {
+    pub fn new(
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlasLayout>,
+    ) -> Self {
+        let mut texture_atlas = TextureAtlasLayout::new_empty(UVec2::new(234, 71));
+        let trees = vec![
+            texture_atlas.add_texture(URect {
+                min: UVec2::new(0, 0),
+                max: UVec2::new(64, 64),
+            }),
+            texture_atlas.add_texture(URect {
+                min: UVec2::new(64, 0),
+                max: UVec2::new(128, 64),
+            }),
+            texture_atlas.add_texture(URect {
+                min: UVec2::new(128, 0),
+                max: UVec2::new(192, 71),
+            }),
+            texture_atlas.add_texture(URect {
+                min: UVec2::new(193, 0),
+                max: UVec2::new(234, 64),
+            }),
+        ];
+
+        Self {
+            trees,
+            trees_layout: texture_atlases.add(texture_atlas),
+            trees_texture: asset_server.load("textures/trees.png"),
+            bush_texture: asset_server.load("textures/bush.png"),
+            grass_layout: texture_atlases.add(TextureAtlasLayout::from_grid(
+                UVec2::new(16, 16),
+                5,
+                2,
+                None,
+                None,
+            )),
+            grass_texture: asset_server.load("textures/grass.png"),
+            cut_grass_texture: asset_server.load("textures/cut-grass.png"),
+        }
+    }
+
+    fn tree(&self, commands: &mut Commands, tree: Tree, x: f32, y: f32, chunk: Chunk) {
+        insert_tree(
+            &self.trees,
+            self.trees_layout.clone(),
+            self.trees_texture.clone(),
+            commands,
+            tree,
+            x,
+            y,
+            chunk,
+        );
+    }
+
+    fn bush(&self, commands: &mut Commands, x: f32, y: f32, chunk: Chunk) {
+        insert_bush(self.bush_texture.clone(), commands, x, y, chunk);
+    }
+
+    fn grass(
+        &self,
+        commands: &mut Commands,
+        delta: &WorldDelta,
+        x: f32,
+        y: f32,
+        max_row: usize,
+        max_col: usize,
+        density: u8,
+        chunk: Chunk,
+        rng: &mut crate::SeedType,
+    ) {
+        insert_grass(
+            self.grass_layout.clone(),
+            self.grass_texture.clone(),
+            commands,
             x,
             y,
+            max_row,
+            max_col,
+            density,
+            chunk,
+            rng,
+            self.cut_grass_texture.clone(),
+            delta,
         );
-        x += 100.;
+    }
+}
+
+fn grass_patch_size(max_row: usize, max_col: usize) -> Vec2 {
+    Vec2::new(
+        max_col as f32 * crate::GRASS_SIZE,
+        max_row as f32 * crate::GRASS_SIZE,
+    )
+}
+
+/// Spawns the trees, bushes and grass listed in the map file.
+///
+/// The areas taken by these elements are added to `used`.
+pub fn spawn_placed_nature(
+    commands: &mut Commands,
+    textures: &NatureTextures,
+    delta: &WorldDelta,
+    chunk: Chunk,
+    objects: &[&MapObject],
+    rng: &mut crate::SeedType,
+    used: &mut Vec<Rect>,
+) {
+    for object in objects {
+        match object.kind {
+            MapObjectKind::Tree(tree) => {
+                textures.tree(commands, tree, object.x, object.y, chunk);
+                used.push(Rect::from_center_size(object.pos(), Vec2::splat(TREE_SIZE)));
+            }
+            MapObjectKind::Bush => {
+                textures.bush(commands, object.x, object.y, chunk);
+                used.push(Rect::from_center_size(object.pos(), Vec2::splat(BUSH_SIZE)));
+            }
+            MapObjectKind::Grass { rows, cols } => {
+                textures.grass(
+                    commands,
+                    delta,
+                    object.x,
+                    object.y,
+                    rows,
+                    cols,
+                    PLACED_GRASS_DENSITY,
+                    chunk,
+                    rng,
+                );
+                let size = grass_patch_size(rows, cols);
+                let top_left = object.pos() + Vec2::new(-1., 1.) * crate::GRASS_SIZE / 2.;
+                used.push(Rect::from_corners(
+                    top_left,
+                    top_left + Vec2::new(size.x, -size.y),
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Generates the trees, bushes and grass of the given chunk depending on its biome.
+///
+/// `used` contains the areas which must be kept free (buildings, statues, etc). The areas taken
+/// by the nature elements are added to it.
+pub fn spawn_nature(
+    commands: &mut Commands,
+    textures: &NatureTextures,
+    delta: &WorldDelta,
+    chunk: Chunk,
+    biome: Biome,
+    rng: &mut crate::SeedType,
+    used: &mut Vec<Rect>,
+) {
+    let area = chunk.rect();
+
+    let biome_trees = biome.trees();
+    for _ in 0..biome.nb_trees() {
+        let tree = biome_trees[rng.gen_range(0..biome_trees.len())];
+        if let Some(pos) = find_free_spot(rng, area, Vec2::splat(TREE_SIZE), used) {
+            textures.tree(commands, tree, pos.x, pos.y, chunk);
+        }
     }
 
-    let grass_texture = asset_server.load("textures/grass.png");
-    let mut texture_atlas = TextureAtlasLayout::from_grid(UVec2::new(16, 16), 5, 2, None, None);
-    let grass_texture_handle = texture_atlases.add(texture_atlas);
-
-    insert_grass(
-        grass_texture_handle.clone(),
-        grass_texture.clone(),
-        &mut commands,
-        -450.,
-        250.,
-        3,
-        10,
-    );
-    insert_grass(
-        grass_texture_handle.clone(),
-        grass_texture.clone(),
-        &mut commands,
-        -500.,
-        400.,
-        3,
-        10,
-    );
+    for _ in 0..biome.nb_bushes() {
+        if let Some(pos) = find_free_spot(rng, area, Vec2::splat(BUSH_SIZE), used) {
+            textures.bush(commands, pos.x, pos.y, chunk);
+        }
+    }
+
+    for _ in 0..biome.nb_grass_patches() {
+        let max_row: usize = rng.gen_range(2..5);
+        let max_col: usize = rng.gen_range(3..11);
+        let size = grass_patch_size(max_row, max_col);
+        if let Some(pos) = find_free_spot(rng, area, size, used) {
+            // `insert_grass` expects the center of the top-left grass.
+            textures.grass(
+                commands,
+                delta,
+                pos.x - size.x / 2. + crate::GRASS_SIZE / 2.,
+                pos.y + size.y / 2. - crate::GRASS_SIZE / 2.,
+                max_row,
+                max_col,
+                biome.grass_density(),
+                chunk,
+                rng,
+            );
+        }
+    }
 }
 
 fn check_if_grass(
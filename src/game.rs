@@ -7,8 +7,9 @@ use once_cell::sync::Lazy;
 
 use crate::menu::MenuState;
 use crate::{
-    building, character, environment, hud, map, monster, player, weapon, AppState, GameInfo,
-    OUTSIDE_WORLD,
+    building, character, death, dungeon, environment, hud, inventory, loot, map, minimap, monster,
+    monster_ai, navigation, player, projectile, spawner, spell, statistics, status_effect, vendor,
+    weapon, world_clock, AppState, GameInfo, OUTSIDE_WORLD,
 };
 
 pub const ONE_SECOND: u32 = 1_000_000;
@@ -20,6 +21,8 @@ pub struct GamePlugin;
 pub struct InsideHouse;
 #[derive(Component)]
 pub struct OutsideWorld;
+#[derive(Component)]
+pub struct InsideDungeon;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum GameState {
@@ -35,6 +38,23 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<GameState>()
             .init_state::<crate::DebugState>()
+            // Changes made to the map must survive chunks being despawned.
+            .init_resource::<crate::world_delta::WorldDelta>()
+            .init_resource::<crate::environment::GrassGrowth>()
+            .init_resource::<world_clock::WorldClock>()
+            .init_resource::<minimap::MapMemory>()
+            .init_resource::<navigation::NavGrids>()
+            .insert_resource(monster::MonsterRegistry::load(monster::MONSTERS_FILE))
+            .add_event::<world_clock::DayPeriodChanged>()
+            .add_event::<character::LevelUp>()
+            .add_event::<weapon::AttackLanded>()
+            .add_event::<weapon::DamageDealt>()
+            .add_event::<weapon::CharacterDied>()
+            .add_event::<weapon::GrassCut>()
+            .init_resource::<statistics::CombatStatistics>()
+            .init_resource::<death::RespawnPoint>()
+            .init_resource::<weapon::CombatRng>()
+            .init_resource::<death::DeathPenalty>()
             .add_systems(
                 Update,
                 (player::player_attack_system,)
@@ -47,12 +67,44 @@ impl Plugin for GamePlugin {
                     weapon::handle_attacks,
                     character::animate_character_system,
                     character::refresh_characters_stats,
+                    character::spawn_level_up_animation.after(character::award_kill_xp),
+                    character::animate_level_up,
+                    death::start_dying.after(death::handle_character_deaths),
+                    death::update_dying,
+                    death::animate_death,
+                    loot::open_containers,
+                    loot::collect_pickups,
+                    spell::cast_spells,
+                    spell::update_spell_effects,
+                    projectile::update_projectiles,
+                    weapon::update_hit_reactions
+                        .after(weapon::handle_attacks)
+                        .after(monster_ai::update_monster_ai),
+                    status_effect::update_status_effects.after(weapon::handle_attacks),
                     hud::update_hud,
+                    hud::update_hud_status_effects,
                     update_camera,
                 )
                     .run_if(in_state(MenuState::Disabled))
                     .after(player::player_movement_system),
             )
+            // Everything reacting to what happened during fights.
+            .add_systems(
+                Update,
+                (
+                    weapon::show_attack_notifications,
+                    death::handle_character_deaths,
+                    character::award_kill_xp,
+                    loot::drop_loot,
+                    weapon::wear_weapons,
+                    environment::cut_grass,
+                    statistics::update_combat_statistics,
+                )
+                    .run_if(in_state(MenuState::Disabled))
+                    .after(weapon::handle_attacks)
+                    .after(monster_ai::update_monster_ai)
+                    .after(status_effect::update_status_effects),
+            )
             .add_systems(
                 Update,
                 (
@@ -60,10 +112,20 @@ impl Plugin for GamePlugin {
                     character::interaction_events,
                     weapon::update_notifications,
                     monster::update_character_info,
+                    status_effect::update_status_effect_icons,
+                    inventory::use_consumables,
+                    monster_ai::update_monster_ai,
                     environment::grass_events,
+                    environment::regrow_grass,
+                    world_clock::update_world_clock,
+                    world_clock::update_tint.after(world_clock::update_world_clock),
+                    hud::update_clock_text.after(world_clock::update_world_clock),
                     weapon::update_entity_destroyer,
                     handle_input,
                     handle_windows,
+                    vendor::talk_to_vendors,
+                    vendor::show_repair_window.after(vendor::talk_to_vendors),
+                    death::show_death_screen,
                 )
                     .run_if(in_state(MenuState::Disabled)),
             )
@@ -96,19 +158,44 @@ impl Plugin for GamePlugin {
                     .run_if(in_state(MenuState::Disabled))
                     .run_if(in_state(GameState::Outside)),
             )
+            .add_systems(
+                Update,
+                (handle_enter_area_events::<InsideDungeon>)
+                    .run_if(in_state(MenuState::Disabled))
+                    .run_if(in_state(GameState::InsideDungeon)),
+            )
+            .add_systems(
+                Update,
+                (map::update_map)
+                    .run_if(in_state(GameState::Outside))
+                    .after(player::player_movement_system),
+            )
+            .add_systems(
+                Update,
+                (
+                    spawner::update_spawners,
+                    minimap::update_map_memory,
+                    death::update_respawn_point,
+                    minimap::show_minimap.after(minimap::update_map_memory),
+                    minimap::show_world_map.after(minimap::update_map_memory),
+                )
+                    .run_if(in_state(MenuState::Disabled))
+                    .run_if(in_state(GameState::Outside))
+                    .after(map::update_map),
+            )
+            .add_systems(
+                PostUpdate,
+                navigation::update_nav_grids
+                    .after(PhysicsSet::Writeback)
+                    .run_if(resource_exists::<map::LoadedChunks>),
+            )
             .add_systems(
                 OnEnter(AppState::Game),
                 (
                     map::spawn_map,
                     player::spawn_player.after(map::spawn_map),
-                    monster::spawn_monsters.after(map::spawn_map),
-                    // TODO: move this into `spawn_map`
-                    building::spawn_buildings,
-                    // TODO: move this into `spawn_map`
-                    building::spawn_statues,
-                    // TODO: move this into `spawn_map`
-                    environment::spawn_nature,
                     hud::build_hud,
+                    world_clock::spawn_night_overlay,
                 ),
             )
             .add_systems(
@@ -118,6 +205,14 @@ impl Plugin for GamePlugin {
             .add_systems(
                 OnExit(GameState::InsideHouse),
                 (crate::despawn_kind::<InsideHouse>, show_outside),
+            )
+            .add_systems(
+                OnEnter(GameState::InsideDungeon),
+                (dungeon::spawn_dungeon, hide_outside),
+            )
+            .add_systems(
+                OnExit(GameState::InsideDungeon),
+                (crate::despawn_kind::<InsideDungeon>, show_outside),
             );
     }
 }
@@ -571,11 +666,18 @@ pub fn handle_input(
     if keyboard_input.just_released(KeyCode::KeyI) {
         app_state.show_inventory_window = !app_state.show_inventory_window;
     }
+    if keyboard_input.just_released(KeyCode::KeyM) {
+        app_state.show_world_map = !app_state.show_world_map;
+    }
     if keyboard_input.just_released(KeyCode::Escape) {
         if app_state.show_character_window {
             app_state.show_character_window = false;
         } else if app_state.show_inventory_window {
             app_state.show_inventory_window = false;
+        } else if app_state.show_world_map {
+            app_state.show_world_map = false;
+        } else if app_state.show_repair_window {
+            app_state.show_repair_window = false;
         } else {
             menu_state.set(MenuState::Main);
         }
@@ -673,7 +775,15 @@ fn handle_door_events<T: Component>(
 
 fn handle_enter_area_events<T: Component>(
     mut collision_events: EventReader<CollisionEvent>,
-    buildings: Query<(&Children, &crate::building::Building), With<T>>,
+    areas: Query<
+        (
+            &Children,
+            &Transform,
+            Option<&building::Building>,
+            Option<&dungeon::DungeonEntrance>,
+        ),
+        With<T>,
+    >,
     enter_area_captors: Query<&building::EnterArea>,
     player: Query<&Transform, With<player::Player>>,
     mut app_state: ResMut<GameInfo>,
@@ -689,34 +799,40 @@ fn handle_enter_area_events<T: Component>(
 
     for collision_event in collision_events.read() {
         if let CollisionEvent::Started(x, y, CollisionEventFlags::SENSOR) = collision_event {
-            let building_id = if *x == player_id {
+            let area_id = if *x == player_id {
                 y
             } else if *y == player_id {
                 x
             } else {
                 continue;
             };
-            if !enter_area_captors.contains(*building_id) {
+            if !enter_area_captors.contains(*area_id) {
                 continue;
             }
-            for (mut children, building) in buildings.iter() {
-                if children.contains(building_id) {
-                    // FIXME: compute real hash
-                    app_state.building_hash = 0;
-                    if *game_state == GameState::Outside {
-                        let player_pos = player.single();
-                        app_state.pos = Vec2 {
-                            x: player_pos.translation.x + crate::MAP_SIZE * 3.,
-                            y: player_pos.translation.y + crate::MAP_SIZE * 3.,
-                        };
-                        app_state.building = Some(*building);
-                        next_game_state.set(GameState::InsideHouse);
-                    } else {
-                        app_state.building = None;
-                        next_game_state.set(GameState::Outside);
-                    }
+            for (children, transform, building, dungeon_entrance) in areas.iter() {
+                if !children.contains(area_id) {
+                    continue;
+                }
+                if *game_state != GameState::Outside {
+                    app_state.building = None;
+                    next_game_state.set(GameState::Outside);
                     return;
                 }
+                let player_pos = player.single();
+                app_state.pos = Vec2 {
+                    x: player_pos.translation.x + crate::MAP_SIZE * 3.,
+                    y: player_pos.translation.y + crate::MAP_SIZE * 3.,
+                };
+                if let Some(building) = building {
+                    app_state.building_hash =
+                        map::hash_pos(transform.translation.x, transform.translation.y);
+                    app_state.building = Some(*building);
+                    next_game_state.set(GameState::InsideHouse);
+                } else if let Some(dungeon_entrance) = dungeon_entrance {
+                    app_state.dungeon_hash = dungeon_entrance.hash;
+                    next_game_state.set(GameState::InsideDungeon);
+                }
+                return;
             }
         }
     }
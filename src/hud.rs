@@ -1,7 +1,10 @@
 use bevy::prelude::*;
 
+use crate::status_effect::{StatusEffectIcons, StatusEffects};
 use crate::{character, player};
 
+const STATUS_EFFECT_ICON_SIZE: f32 = 10.;
+
 #[derive(Component)]
 pub struct Hud;
 
@@ -60,6 +63,20 @@ pub fn build_hud(
     );
     spawn_stat_bar(&mut commands, StatKind::Mana, crate::CYAN);
     spawn_stat_bar(&mut commands, StatKind::Stamina, crate::YELLOW);
+    // The status effects icons are displayed on the right of the stat bars.
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                left: Val::Px(110.0),
+                top: Val::Px(4.0),
+                column_gap: Val::Px(2.0),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            ..default()
+        },
+        StatusEffectIcons::default(),
+    ));
 
     let font = asset_server.load(crate::FONT);
     let mut text_bundle = TextBundle::from_section(
@@ -79,6 +96,24 @@ pub fn build_hud(
     });
     text_bundle.visibility = Visibility::Hidden;
     commands.spawn((text_bundle, DebugText));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load(crate::FONT),
+                font_size: 30.0 / crate::SCALE,
+                color: Color::LinearRgba(LinearRgba::WHITE),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(2.0),
+            right: Val::Px(2.0),
+            ..default()
+        }),
+        ClockText,
+    ));
 }
 
 pub fn update_hud(
@@ -99,9 +134,70 @@ pub fn update_hud(
     }
 }
 
+pub fn update_hud_status_effects(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    player: Query<Option<&StatusEffects>, With<player::Player>>,
+    mut icons: Query<(Entity, &mut StatusEffectIcons), With<Node>>,
+) {
+    let kinds = match player.get_single() {
+        Ok(effects) => effects.map(|effects| effects.kinds()).unwrap_or_default(),
+        _ => return,
+    };
+    let (holder, mut displayed) = match icons.get_single_mut() {
+        Ok(icons) => icons,
+        _ => return,
+    };
+    if displayed.0 == kinds {
+        return;
+    }
+    displayed.0 = kinds.clone();
+    commands
+        .entity(holder)
+        .despawn_descendants()
+        .with_children(|parent| {
+            for kind in kinds {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(STATUS_EFFECT_ICON_SIZE),
+                            height: Val::Px(STATUS_EFFECT_ICON_SIZE),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::LinearRgba(kind.color()).into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            kind.symbol(),
+                            TextStyle {
+                                font: asset_server.load(crate::FONT),
+                                font_size: 8.0,
+                                color: Color::LinearRgba(LinearRgba::BLACK),
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
 #[derive(Component)]
 pub struct DebugText;
 
+#[derive(Component)]
+pub struct ClockText;
+
+pub fn update_clock_text(
+    mut text: Query<&mut Text, With<ClockText>>,
+    clock: Res<crate::world_clock::WorldClock>,
+) {
+    for mut text in text.iter_mut() {
+        text.sections[0].value = clock.display();
+    }
+}
+
 pub fn update_text(
     mut text: Query<&mut Text, With<DebugText>>,
     camera: Query<&Transform, With<Camera>>,
@@ -1,11 +1,58 @@
-use bevy::prelude::Component;
+use bevy::prelude::*;
+use serde::Deserialize;
 
-use crate::weapon::Weapon;
+use crate::character::Character;
+use crate::death::Dying;
+use crate::player::Player;
+use crate::status_effect::{apply_status_effect, StatusEffect, StatusEffects};
+use crate::weapon::{spawn_notification, Weapon};
 
-#[derive(Debug)]
+const CONSUMABLE_COLOR: LinearRgba = LinearRgba::rgb(0.3, 1., 0.4);
+
+/// Something which is used up when the player presses E.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Consumable {
+    /// How much health it gives back.
+    #[serde(default)]
+    pub health: f32,
+    /// If `true`, it removes all the status effects.
+    #[serde(default)]
+    pub cures: bool,
+    #[serde(default)]
+    pub effect: Option<StatusEffect>,
+}
+
+impl Consumable {
+    pub fn healing_potion() -> Self {
+        Self {
+            health: 30.,
+            cures: false,
+            effect: None,
+        }
+    }
+
+    pub fn antidote() -> Self {
+        Self {
+            health: 0.,
+            cures: true,
+            effect: None,
+        }
+    }
+
+    /// Returns `false` if using it right now would be a waste, like drinking a healing potion with
+    /// full health.
+    fn would_have_effect(&self, character: &Character, effects: Option<&StatusEffects>) -> bool {
+        (self.health > 0. && character.stats.health.value() < character.stats.health.max_value())
+            || (self.cures && effects.map_or(false, |effects| !effects.is_empty()))
+            || self.effect.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub enum InventoryItem {
     Weapon(Weapon),
     Collectible { quantity: u16 },
+    Consumable(Consumable),
 }
 
 #[derive(Debug, Component)]
@@ -28,4 +75,101 @@ impl Inventory {
             equipped_weapon,
         }
     }
+
+    /// Puts `item` in the first free slot. Collectibles are stacked together.
+    ///
+    /// Returns `false` if there is no room left.
+    pub fn add_item(&mut self, item: InventoryItem) -> bool {
+        if let InventoryItem::Collectible { quantity } = item {
+            for slot in self.items.iter_mut() {
+                if let Some(InventoryItem::Collectible { quantity: stack }) = slot {
+                    *stack = stack.saturating_add(quantity);
+                    return true;
+                }
+            }
+        }
+        match self.items.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(item);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes `quantity` collectibles from the inventory, emptying the slot if none is left.
+    ///
+    /// Returns `false` (and removes nothing) if there aren't enough of them.
+    pub fn consume_collectible(&mut self, quantity: u16) -> bool {
+        for slot in self.items.iter_mut() {
+            if let Some(InventoryItem::Collectible { quantity: stack }) = slot {
+                if *stack < quantity {
+                    return false;
+                }
+                *stack -= quantity;
+                if *stack == 0 {
+                    *slot = None;
+                }
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Uses the first consumable of the inventory which would have an effect when E is pressed.
+pub fn use_consumables(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Character,
+            &mut Inventory,
+            Option<&mut StatusEffects>,
+        ),
+        (With<Player>, Without<Dying>),
+    >,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyE) {
+        return;
+    }
+    let (player_id, mut character, mut inventory, effects) = match player.get_single_mut() {
+        Ok(p) => p,
+        _ => return,
+    };
+    let consumable = match inventory
+        .items
+        .iter_mut()
+        .find(|slot| match slot {
+            Some(InventoryItem::Consumable(consumable)) => {
+                consumable.would_have_effect(&character, effects.as_deref())
+            }
+            _ => false,
+        })
+        .and_then(|slot| slot.take())
+    {
+        Some(InventoryItem::Consumable(consumable)) => consumable,
+        _ => return,
+    };
+    if consumable.cures {
+        if let Some(mut effects) = effects {
+            effects.clear();
+        }
+    }
+    if let Some(effect) = consumable.effect {
+        apply_status_effect(&mut commands, player_id, effect);
+    }
+    if consumable.health > 0. {
+        character.stats.health.add(consumable.health);
+        spawn_notification(
+            &mut commands,
+            &asset_server,
+            player_id,
+            character.height,
+            &format!("+{:.0}", consumable.health),
+            CONSUMABLE_COLOR,
+        );
+    }
 }
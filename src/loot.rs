@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::character::Character;
+use crate::death::Dying;
+use crate::game::{InsideDungeon, InsideHouse, OutsideWorld};
+use crate::inventory::{Inventory, InventoryItem};
+use crate::map::Chunk;
+use crate::player::Player;
+use crate::weapon::{spawn_notification, CharacterDied};
+
+/// Pickups closer than this from the player are picked up automatically.
+const PICKUP_RADIUS: f32 = 12.;
+/// Distance from which the player can pick up something or open a container by pressing ENTER.
+const INTERACT_RANGE: f32 = 30.;
+/// Dropped pickups are scattered between these distances around where they were dropped.
+const SCATTER_MIN: f32 = 10.;
+const SCATTER_MAX: f32 = 24.;
+const GOLD_COLOR: LinearRgba = LinearRgba::rgb(1., 0.85, 0.2);
+const COLLECTIBLE_COLOR: LinearRgba = LinearRgba::rgb(0.4, 0.9, 1.);
+const CONSUMABLE_COLOR: LinearRgba = LinearRgba::rgb(1., 0.4, 0.6);
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum LootDrop {
+    Gold { min: u32, max: u32 },
+    Item(InventoryItem),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LootEntry {
+    pub weight: u32,
+    /// `None` means that nothing is dropped.
+    pub drop: Option<LootDrop>,
+}
+
+/// What a monster drops when it dies or what is found in a container.
+#[derive(Debug, Clone, Default, Component, Deserialize)]
+pub struct LootTable {
+    /// How many times an entry is picked.
+    pub rolls: u32,
+    pub entries: Vec<LootEntry>,
+}
+
+impl LootTable {
+    pub fn roll<R: Rng>(&self, rng: &mut R) -> Vec<Pickup> {
+        let total_weight: u32 = self.entries.iter().map(|entry| entry.weight).sum();
+        if total_weight == 0 {
+            return Vec::new();
+        }
+        let mut pickups = Vec::new();
+        for _ in 0..self.rolls {
+            let mut value = rng.gen_range(0..total_weight);
+            let entry = self
+                .entries
+                .iter()
+                .find(|entry| {
+                    if value < entry.weight {
+                        true
+                    } else {
+                        value -= entry.weight;
+                        false
+                    }
+                })
+                .unwrap();
+            match &entry.drop {
+                Some(LootDrop::Gold { min, max }) => {
+                    // The bounds might have been swapped in the definition file.
+                    let gold = rng.gen_range(*min.min(max)..=*min.max(max));
+                    pickups.push(Pickup::Gold(gold))
+                }
+                Some(LootDrop::Item(item)) => pickups.push(Pickup::Item(item.clone())),
+                None => {}
+            }
+        }
+        pickups
+    }
+}
+
+/// Something lying on the ground, waiting to be picked up by the player.
+#[derive(Debug, Clone, Component)]
+pub enum Pickup {
+    Gold(u32),
+    Item(InventoryItem),
+}
+
+/// Spawns `pickups` around `pos`. `chunk`, `in_dungeon` and `in_house` tell to which area the
+/// pickups belong so they're despawned with it.
+fn spawn_pickups(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    pos: Vec2,
+    pickups: Vec<Pickup>,
+    chunk: Option<&Chunk>,
+    in_dungeon: bool,
+    in_house: bool,
+) {
+    let mut rng = rand::thread_rng();
+    for pickup in pickups {
+        let angle = rng.gen_range(0. ..std::f32::consts::TAU);
+        let pos = pos + Vec2::from_angle(angle) * rng.gen_range(SCATTER_MIN..SCATTER_MAX);
+        let (texture, size, color) = match &pickup {
+            Pickup::Gold(_) => ("textures/gold-coin.png", Vec2::splat(10.), Color::WHITE),
+            Pickup::Item(InventoryItem::Weapon(weapon)) => (
+                weapon.kind.texture(),
+                Vec2::new(weapon.width, weapon.height),
+                Color::WHITE,
+            ),
+            Pickup::Item(InventoryItem::Collectible { .. }) => (
+                "textures/gold-coin.png",
+                Vec2::splat(8.),
+                Color::LinearRgba(COLLECTIBLE_COLOR),
+            ),
+            Pickup::Item(InventoryItem::Consumable(_)) => (
+                "textures/potion.png",
+                Vec2::splat(10.),
+                Color::LinearRgba(CONSUMABLE_COLOR),
+            ),
+        };
+        let mut entity = commands.spawn((
+            pickup,
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(size),
+                    color,
+                    ..default()
+                },
+                texture: asset_server.load(texture),
+                transform: Transform::from_xyz(pos.x, pos.y, crate::CARPET_SYMBOL_Z_INDEX),
+                ..default()
+            },
+        ));
+        if let Some(chunk) = chunk {
+            entity.insert((OutsideWorld, *chunk));
+        } else if in_dungeon {
+            entity.insert(InsideDungeon);
+        } else if in_house {
+            entity.insert(InsideHouse);
+        }
+    }
+}
+
+/// Drops the gold and the loot of the monsters which just died.
+pub fn drop_loot(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut deaths: EventReader<CharacterDied>,
+    dead: Query<
+        (
+            &Transform,
+            Option<&LootTable>,
+            Option<&Inventory>,
+            Option<&Chunk>,
+            Has<InsideDungeon>,
+            Has<InsideHouse>,
+        ),
+        Without<Player>,
+    >,
+) {
+    let mut rng = rand::thread_rng();
+    // A character can be killed by more than one attack at once.
+    let mut looted = Vec::new();
+    for death in deaths.read() {
+        if looted.contains(&death.entity) {
+            continue;
+        }
+        let (transform, loot, inventory, chunk, in_dungeon, in_house) = match dead.get(death.entity)
+        {
+            Ok(dead) => dead,
+            Err(_) => continue,
+        };
+        looted.push(death.entity);
+        let mut pickups = loot.map(|loot| loot.roll(&mut rng)).unwrap_or_default();
+        if let Some(inventory) = inventory.filter(|inventory| inventory.gold > 0) {
+            pickups.push(Pickup::Gold(inventory.gold));
+        }
+        spawn_pickups(
+            &mut commands,
+            &asset_server,
+            transform.translation.truncate(),
+            pickups,
+            chunk,
+            in_dungeon,
+            in_house,
+        );
+    }
+}
+
+/// Opens the closest container when the player presses ENTER next to it. Containers are emptied
+/// once opened.
+pub fn open_containers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    player: Query<&Transform, (With<Player>, Without<Dying>)>,
+    containers: Query<
+        (
+            Entity,
+            &Transform,
+            &LootTable,
+            Option<&Chunk>,
+            Has<InsideDungeon>,
+            Has<InsideHouse>,
+        ),
+        Without<Character>,
+    >,
+) {
+    if !keyboard_input.just_released(KeyCode::Enter) {
+        return;
+    }
+    let player = match player.get_single() {
+        Ok(p) => p.translation.truncate(),
+        _ => return,
+    };
+    let container = containers
+        .iter()
+        .map(|container| {
+            let distance = container.1.translation.truncate().distance(player);
+            (container, distance)
+        })
+        .filter(|(_, distance)| *distance < INTERACT_RANGE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+    if let Some(((entity, transform, loot, chunk, in_dungeon, in_house), _)) = container {
+        let pickups = loot.roll(&mut rand::thread_rng());
+        spawn_pickups(
+            &mut commands,
+            &asset_server,
+            transform.translation.truncate(),
+            pickups,
+            chunk,
+            in_dungeon,
+            in_house,
+        );
+        commands.entity(entity).remove::<LootTable>();
+    }
+}
+
+/// Moves the pickups the player walks over (or the closest one if ENTER is pressed) into their
+/// inventory.
+pub fn collect_pickups(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut player: Query<
+        (Entity, &Transform, &Character, &mut Inventory),
+        (With<Player>, Without<Dying>),
+    >,
+    pickups: Query<(Entity, &Transform, &Pickup)>,
+) {
+    let (player_id, player_pos, character, mut inventory) = match player.get_single_mut() {
+        Ok(p) => p,
+        _ => return,
+    };
+    let player_pos = player_pos.translation.truncate();
+    let interact = keyboard_input.just_released(KeyCode::Enter);
+    let closest = pickups
+        .iter()
+        .map(|(entity, transform, _)| {
+            (
+                entity,
+                transform.translation.truncate().distance(player_pos),
+            )
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    for (entity, transform, pickup) in pickups.iter() {
+        let distance = transform.translation.truncate().distance(player_pos);
+        let is_interacting =
+            interact && distance < INTERACT_RANGE && closest.map(|(e, _)| e) == Some(entity);
+        if distance >= PICKUP_RADIUS && !is_interacting {
+            continue;
+        }
+        let (text, color) = match pickup {
+            Pickup::Gold(gold) => {
+                inventory.gold += gold;
+                (format!("+{} gold", gold), GOLD_COLOR)
+            }
+            Pickup::Item(item) => {
+                if !inventory.add_item(item.clone()) {
+                    if is_interacting {
+                        spawn_notification(
+                            &mut commands,
+                            &asset_server,
+                            player_id,
+                            character.height,
+                            "Inventory full",
+                            LinearRgba::WHITE,
+                        );
+                    }
+                    continue;
+                }
+                let text = match item {
+                    InventoryItem::Weapon(_) => "+1 weapon".to_owned(),
+                    InventoryItem::Collectible { quantity } => format!("+{} collectible", quantity),
+                    InventoryItem::Consumable(_) => "+1 consumable".to_owned(),
+                };
+                (text, COLLECTIBLE_COLOR)
+            }
+        };
+        spawn_notification(
+            &mut commands,
+            &asset_server,
+            player_id,
+            character.height,
+            &text,
+            color,
+        );
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,18 +1,33 @@
 #![allow(unused)]
 
+mod biome;
 mod building;
 mod character;
+mod death;
+mod dungeon;
 mod environment;
 mod game;
 mod hud;
 mod inventory;
+mod loot;
 mod map;
+mod map_file;
 mod menu;
+mod minimap;
 mod monster;
+mod monster_ai;
+mod navigation;
 mod player;
+mod projectile;
+mod spawner;
+mod spell;
 mod stat;
+mod statistics;
+mod status_effect;
 mod vendor;
 mod weapon;
+mod world_clock;
+mod world_delta;
 
 use bevy::input::InputPlugin;
 use bevy::prelude::*;
@@ -34,7 +49,8 @@ pub const INTERACTION: Group = Group::GROUP_2;
 pub const HITBOX: Group = Group::GROUP_3;
 pub const NOTHING: Group = Group::GROUP_4;
 pub const RUN_STAMINA_CONSUMPTION_PER_SEC: f32 = 10.;
-pub const MAP_SIZE: f32 = 10_000.;
+// Size of a map chunk.
+pub const MAP_SIZE: f32 = 2_000.;
 
 pub const SCALE: f32 = 1.8;
 
@@ -77,12 +93,23 @@ pub enum DebugState {
 pub struct GameInfo {
     pub show_character_window: bool,
     pub show_inventory_window: bool,
+    pub show_world_map: bool,
+    pub show_repair_window: bool,
     pub player_id: Option<Entity>,
     pub building_hash: u32,
+    pub dungeon_hash: u32,
     pub building: Option<building::Building>,
     pub pos: Vec2,
 }
 
+/// Returns the path of a file of the `assets` folder. It's resolved like the `AssetServer` does, so
+/// it doesn't depend on the directory the game is started from.
+pub fn asset_path(path: &str) -> std::path::PathBuf {
+    bevy::asset::io::file::FileAssetReader::get_base_path()
+        .join("assets")
+        .join(path)
+}
+
 pub fn despawn_kind<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
         commands.entity(entity).despawn_recursive();
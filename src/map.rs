@@ -1,25 +1,322 @@
+use bevy::math::Rect;
 use bevy::prelude::*;
-use bevy_rapier2d::prelude::*;
+use bevy::utils::HashSet;
+use rand::Rng;
+use rand_seeder::Seeder;
 
+use crate::biome::Biome;
+use crate::game::OutsideWorld;
+use crate::map_file::{MapFile, MapObjectKind, WORLD_MAP_FILE};
+use crate::monster::{MonsterRegistry, MONSTER_SIZE};
 use crate::player::Player;
-use crate::MAP_SIZE;
+use crate::world_clock::WorldClock;
+use crate::world_delta::WorldDelta;
+use crate::{building, dungeon, environment, spawner, MAP_SIZE};
 
-// The first map is at (0, 0). Then every time we go (MAP_SIZE / 2) to a given direction, we
-// increase `x` or `xy` (or both) by 1.
-pub fn create_map_for_pos(mut commands: Commands, x: u32, y: u32) {
-    // commands
-    //     .spawn_bundle(TransformBundle::from(Transform::from_xyz(
-    //         x as f32 * MAP_SIZE - MAP_SIZE / 2.,
-    //         y as f32 * MAP_SIZE - MAP_SIZE / 2.,
-    //         0.0,
-    //     )))
-    //     .insert(Collider::cuboid(MAP_SIZE, MAP_SIZE));
-    // TODO: spawn environment.
+/// How many chunks around the one the player is in are kept alive in each direction.
+const CHUNK_LOAD_RADIUS: i32 = 1;
+/// Minimum space kept between two elements placed on the map.
+const PLACEMENT_SPACING: f32 = 8.;
+/// How many times we try to find a free spot for an element before giving up on it.
+const MAX_PLACEMENT_ATTEMPTS: usize = 30;
+/// How many monsters a generated spawner keeps alive.
+const MONSTERS_PER_SPAWNER: usize = 3;
+/// Radius around a generated spawner in which its monsters appear.
+const SPAWNER_RADIUS: f32 = 150.;
+
+/// Every entity spawned as part of a chunk holds this component so it can be despawned once the
+/// player is far enough.
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chunk {
+    pub x: i32,
+    pub y: i32,
 }
 
-pub fn spawn_map(mut commands: Commands) {
-    create_map_for_pos(
-        commands, // FIXME: needs the position where the player is loaded.
-        0, 0,
+impl Chunk {
+    pub const ORIGIN: Self = Self { x: 0, y: 0 };
+
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    // The first map is at (0, 0) and is centered on the origin. Then every time we go `MAP_SIZE`
+    // further in a given direction, we increase (or decrease) `x` or `y` (or both) by 1.
+    pub fn from_pos(x: f32, y: f32) -> Self {
+        Self {
+            x: ((x + MAP_SIZE / 2.) / MAP_SIZE).floor() as _,
+            y: ((y + MAP_SIZE / 2.) / MAP_SIZE).floor() as _,
+        }
+    }
+
+    pub fn center(self) -> Vec2 {
+        Vec2::new(self.x as f32 * MAP_SIZE, self.y as f32 * MAP_SIZE)
+    }
+
+    pub fn rect(self) -> Rect {
+        Rect::from_center_size(self.center(), Vec2::splat(MAP_SIZE))
+    }
+
+    /// Returns the random generator for this chunk. It is derived from `SEED` so the same chunk
+    /// is always generated the same way.
+    pub fn rng(self) -> crate::SeedType {
+        Seeder::from(&format!("{};{};{}", crate::SEED, self.x, self.y)).make_rng()
+    }
+
+    fn is_near(self, other: Self) -> bool {
+        (self.x - other.x).abs() <= CHUNK_LOAD_RADIUS
+            && (self.y - other.y).abs() <= CHUNK_LOAD_RADIUS
+    }
+}
+
+/// Returns a hash of the given position, derived from `SEED`. It is used to generate the content of
+/// what can be entered from the map (like dungeons).
+pub fn hash_pos(x: f32, y: f32) -> u32 {
+    let mut rng: crate::SeedType =
+        Seeder::from(&format!("{};{:.1};{:.1}", crate::SEED, x, y)).make_rng();
+    rng.gen()
+}
+
+/// The chunks currently spawned.
+#[derive(Default, Resource)]
+pub struct LoadedChunks {
+    chunks: HashSet<Chunk>,
+}
+
+impl LoadedChunks {
+    pub fn contains(&self, chunk: Chunk) -> bool {
+        self.chunks.contains(&chunk)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Chunk> + '_ {
+        self.chunks.iter().copied()
+    }
+}
+
+// Dart throwing ("Poisson-disc" style): we pick random positions in the area until we find one
+// which doesn't overlap with anything already placed.
+pub fn find_free_spot(
+    rng: &mut crate::SeedType,
+    area: Rect,
+    size: Vec2,
+    used: &mut Vec<Rect>,
+) -> Option<Vec2> {
+    // The ranges below would be empty.
+    if area.width() <= size.x || area.height() <= size.y {
+        return None;
+    }
+    let half_size = size / 2.;
+    for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+        let center = Vec2::new(
+            rng.gen_range(area.min.x + half_size.x..area.max.x - half_size.x),
+            rng.gen_range(area.min.y + half_size.y..area.max.y - half_size.y),
+        );
+        let footprint = Rect::from_center_size(center, size);
+        let with_spacing = footprint.inflate(PLACEMENT_SPACING);
+        if used
+            .iter()
+            .all(|rect| rect.intersect(with_spacing).is_empty())
+        {
+            used.push(footprint);
+            return Some(center);
+        }
+    }
+    None
+}
+
+pub fn create_map_for_pos(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlasLayout>,
+    monsters: &MonsterRegistry,
+    map_file: &MapFile,
+    world_delta: &WorldDelta,
+    is_night: bool,
+    chunk: Chunk,
+) {
+    let biome = Biome::for_chunk(chunk);
+    let area = chunk.rect();
+    let mut rng = chunk.rng();
+    let mut obstacles = Vec::new();
+    let textures = environment::NatureTextures::new(asset_server, texture_atlases);
+
+    // First we spawn what was placed by hand.
+    let objects = map_file.objects_in(chunk);
+    building::spawn_buildings(
+        commands,
+        asset_server,
+        texture_atlases,
+        chunk,
+        &objects,
+        &mut obstacles,
+    );
+    building::spawn_statues(
+        commands,
+        asset_server,
+        texture_atlases,
+        chunk,
+        &objects,
+        &mut obstacles,
+    );
+    environment::spawn_placed_nature(
+        commands,
+        &textures,
+        world_delta,
+        chunk,
+        &objects,
+        &mut rng,
+        &mut obstacles,
+    );
+    for object in objects {
+        match object.kind {
+            MapObjectKind::Monster { kind, level } => {
+                kind.spawn(
+                    commands,
+                    asset_server,
+                    texture_atlases,
+                    monsters,
+                    object.x,
+                    object.y,
+                    level,
+                    (OutsideWorld, chunk),
+                );
+                obstacles.push(Rect::from_center_size(
+                    object.pos(),
+                    Vec2::splat(MONSTER_SIZE),
+                ));
+            }
+            MapObjectKind::Spawner {
+                kind,
+                max_monsters,
+                radius,
+            } => {
+                spawner::insert_spawner(
+                    commands,
+                    asset_server,
+                    texture_atlases,
+                    monsters,
+                    &mut rng,
+                    &mut obstacles,
+                    kind,
+                    object.pos(),
+                    max_monsters,
+                    radius,
+                    is_night,
+                    chunk,
+                );
+            }
+            MapObjectKind::DungeonEntrance => {
+                obstacles.push(dungeon::insert_dungeon_entrance(
+                    commands, object.x, object.y, chunk,
+                ));
+            }
+            MapObjectKind::Clear { width, height } => {
+                obstacles.push(Rect::from_center_size(
+                    object.pos(),
+                    Vec2::new(width, height),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    // Then we fill the rest of the chunk.
+    for _ in 0..biome.nb_dungeon_entrances() {
+        if let Some(pos) =
+            find_free_spot(&mut rng, area, dungeon::ENTRANCE_FOOTPRINT, &mut obstacles)
+        {
+            dungeon::insert_dungeon_entrance(commands, pos.x, pos.y, chunk);
+        }
+    }
+    environment::spawn_nature(
+        commands,
+        &textures,
+        world_delta,
+        chunk,
+        biome,
+        &mut rng,
+        &mut obstacles,
     );
+
+    let kinds = biome.monsters();
+    if kinds.is_empty() {
+        return;
+    }
+    let size = Vec2::splat(MONSTER_SIZE);
+    let mut nb_monsters = biome.nb_monsters();
+    while nb_monsters > 0 {
+        let kind = kinds[rng.gen_range(0..kinds.len())];
+        let max_monsters = nb_monsters.min(MONSTERS_PER_SPAWNER);
+        nb_monsters -= max_monsters;
+        // The spawner itself doesn't take any room, its monsters do.
+        if let Some(pos) = find_free_spot(&mut rng, area, size, &mut Vec::new()) {
+            spawner::insert_spawner(
+                commands,
+                asset_server,
+                texture_atlases,
+                monsters,
+                &mut rng,
+                &mut obstacles,
+                kind,
+                pos,
+                max_monsters,
+                SPAWNER_RADIUS,
+                is_night,
+                chunk,
+            );
+        }
+    }
+}
+
+pub fn spawn_map(mut commands: Commands) {
+    commands.insert_resource(MapFile::load(WORLD_MAP_FILE));
+    // Chunks are spawned by `update_map` once the player position is known.
+    commands.insert_resource(LoadedChunks::default());
+}
+
+/// Spawns the chunks around the player and despawns the ones which are too far away.
+pub fn update_map(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    mut loaded_chunks: ResMut<LoadedChunks>,
+    monsters: Res<MonsterRegistry>,
+    map_file: Res<MapFile>,
+    world_delta: Res<WorldDelta>,
+    clock: Res<WorldClock>,
+    player: Query<&Transform, (With<Player>, Changed<Transform>)>,
+    chunk_entities: Query<(Entity, &Chunk)>,
+) {
+    let player = match player.get_single() {
+        Ok(p) => p,
+        _ => return,
+    };
+    let current = Chunk::from_pos(player.translation.x, player.translation.y);
+
+    let nb_loaded = loaded_chunks.chunks.len();
+    loaded_chunks.chunks.retain(|chunk| chunk.is_near(current));
+    if nb_loaded != loaded_chunks.chunks.len() {
+        for (entity, chunk) in chunk_entities.iter() {
+            if !chunk.is_near(current) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+
+    for x in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+        for y in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+            let chunk = Chunk::new(current.x + x, current.y + y);
+            if loaded_chunks.chunks.insert(chunk) {
+                create_map_for_pos(
+                    &mut commands,
+                    &asset_server,
+                    &mut texture_atlases,
+                    &monsters,
+                    &map_file,
+                    &world_delta,
+                    clock.is_night(),
+                    chunk,
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::building::{Building, Statue};
+use crate::environment::Tree;
+use crate::map::Chunk;
+use crate::monster::MonsterKind;
+use crate::spawner::MIN_SPAWNER_RADIUS;
+
+/// The hand-authored part of the world (relative to the `assets` folder).
+pub const WORLD_MAP_FILE: &str = "maps/world.ron";
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum MapObjectKind {
+    Building(Building),
+    Statue(Statue),
+    Tree(Tree),
+    Bush,
+    /// `x` and `y` are the position of the top-left grass.
+    Grass {
+        rows: usize,
+        cols: usize,
+    },
+    Monster {
+        kind: MonsterKind,
+        level: u16,
+    },
+    /// Keeps `max_monsters` monsters alive within `radius` around it. Their level depends on
+    /// the distance from the origin.
+    Spawner {
+        kind: MonsterKind,
+        max_monsters: usize,
+        radius: f32,
+    },
+    DungeonEntrance,
+    /// An area where nothing will be generated.
+    Clear {
+        width: f32,
+        height: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct MapObject {
+    pub kind: MapObjectKind,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl MapObject {
+    pub fn pos(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+/// Objects placed on the map. Each object belongs to the chunk its position is in.
+#[derive(Debug, Default, Deserialize, Resource)]
+pub struct MapFile {
+    pub objects: Vec<MapObject>,
+}
+
+impl MapFile {
+    pub fn load(path: &str) -> Self {
+        let content = match std::fs::read_to_string(crate::asset_path(path)) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Failed to read map file `{}`: {}", path, error);
+                return Self::default();
+            }
+        };
+        let mut map: Self = match ron::from_str(&content) {
+            Ok(map) => map,
+            Err(error) => {
+                error!("Failed to parse map file `{}`: {}", path, error);
+                return Self::default();
+            }
+        };
+        map.validate(path);
+        map
+    }
+
+    /// Fixes the values which would prevent objects from being generated.
+    fn validate(&mut self, path: &str) {
+        for object in self.objects.iter_mut() {
+            if let MapObjectKind::Spawner { ref mut radius, .. } = object.kind {
+                if radius.is_nan() || *radius < MIN_SPAWNER_RADIUS {
+                    error!(
+                        "Spawner at ({}, {}) in `{}` has a radius of {}, using {} instead",
+                        object.x, object.y, path, radius, MIN_SPAWNER_RADIUS
+                    );
+                    *radius = MIN_SPAWNER_RADIUS;
+                }
+            }
+        }
+    }
+
+    pub fn objects_in(&self, chunk: Chunk) -> Vec<&MapObject> {
+        self.objects
+            .iter()
+            .filter(|object| Chunk::from_pos(object.x, object.y) == chunk)
+            .collect()
+    }
+}
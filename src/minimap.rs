@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::building::{Building, Statue};
+use crate::game::OutsideWorld;
+use crate::map::Chunk;
+use crate::player::Player;
+use crate::{GameInfo, MAP_SIZE};
+
+/// Size (in pixels) of the minimap.
+const MINIMAP_SIZE: f32 = 150.;
+/// How much of the world (in world units) is displayed in the minimap.
+const MINIMAP_RANGE: f32 = 1_500.;
+/// Size (in pixels) of the world map.
+const WORLD_MAP_SIZE: f32 = 400.;
+
+const UNEXPLORED_COLOR: egui::Color32 = egui::Color32::from_rgb(15, 15, 15);
+const EXPLORED_COLOR: egui::Color32 = egui::Color32::from_rgb(46, 80, 38);
+const HOUSE_COLOR: egui::Color32 = egui::Color32::from_rgb(140, 90, 50);
+const SHOP_COLOR: egui::Color32 = egui::Color32::from_rgb(220, 180, 60);
+const STATUE_COLOR: egui::Color32 = egui::Color32::from_rgb(180, 180, 190);
+const PLAYER_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 40, 40);
+
+#[derive(Debug, Clone, Copy)]
+enum Landmark {
+    Building(Building),
+    Statue,
+}
+
+/// What the player discovered of the world so far.
+#[derive(Debug, Default, Resource)]
+pub struct MapMemory {
+    explored: HashSet<Chunk>,
+    /// Landmarks stored by (rounded) position so they aren't duplicated when their chunk is
+    /// spawned again.
+    landmarks: HashMap<IVec2, (Landmark, Vec2)>,
+}
+
+pub fn update_map_memory(
+    mut memory: ResMut<MapMemory>,
+    player: Query<&Transform, (With<Player>, Changed<Transform>)>,
+    buildings: Query<(&Building, &Transform), (With<OutsideWorld>, Added<Building>)>,
+    statues: Query<&Transform, (With<OutsideWorld>, Added<Statue>)>,
+) {
+    if let Ok(player) = player.get_single() {
+        memory
+            .explored
+            .insert(Chunk::from_pos(player.translation.x, player.translation.y));
+    }
+    for (building, transform) in buildings.iter() {
+        let pos = transform.translation.truncate();
+        memory
+            .landmarks
+            .insert(pos.round().as_ivec2(), (Landmark::Building(*building), pos));
+    }
+    for transform in statues.iter() {
+        let pos = transform.translation.truncate();
+        memory
+            .landmarks
+            .insert(pos.round().as_ivec2(), (Landmark::Statue, pos));
+    }
+}
+
+/// Draws the map in `rect`. `center` is the world position displayed in the middle of `rect` and
+/// `scale` is the number of pixels for one world unit.
+fn draw_map(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    center: Vec2,
+    scale: f32,
+    memory: &MapMemory,
+    player: Vec2,
+) {
+    let painter = painter.with_clip_rect(rect);
+    let to_screen = |pos: Vec2| {
+        let diff = (pos - center) * scale;
+        // The y axis goes down in egui.
+        rect.center() + egui::Vec2::new(diff.x, -diff.y)
+    };
+
+    painter.rect_filled(rect, 0., UNEXPLORED_COLOR);
+    for chunk in memory.explored.iter() {
+        let chunk_rect = chunk.rect();
+        painter.rect_filled(
+            egui::Rect::from_two_pos(to_screen(chunk_rect.min), to_screen(chunk_rect.max)),
+            0.,
+            EXPLORED_COLOR,
+        );
+    }
+    for (landmark, pos) in memory.landmarks.values() {
+        let pos = to_screen(*pos);
+        match landmark {
+            Landmark::Building(building) => {
+                let color = if *building == Building::House {
+                    HOUSE_COLOR
+                } else {
+                    SHOP_COLOR
+                };
+                painter.rect_filled(
+                    egui::Rect::from_center_size(pos, egui::Vec2::splat(5.)),
+                    0.,
+                    color,
+                );
+            }
+            Landmark::Statue => painter.circle_filled(pos, 2., STATUE_COLOR),
+        }
+    }
+    painter.circle_filled(to_screen(player), 3., PLAYER_COLOR);
+    painter.rect_stroke(rect, 0., egui::Stroke::new(1., egui::Color32::BLACK));
+}
+
+pub fn show_minimap(
+    mut egui_context: EguiContexts,
+    memory: Res<MapMemory>,
+    player: Query<&Transform, With<Player>>,
+) {
+    let player = match player.get_single() {
+        Ok(p) => p.translation.truncate(),
+        _ => return,
+    };
+
+    egui::Area::new(egui::Id::new("minimap"))
+        .anchor(egui::Align2::LEFT_BOTTOM, [4., -4.])
+        .interactable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            let (response, painter) =
+                ui.allocate_painter(egui::Vec2::splat(MINIMAP_SIZE), egui::Sense::hover());
+            draw_map(
+                &painter,
+                response.rect,
+                player,
+                MINIMAP_SIZE / MINIMAP_RANGE,
+                &memory,
+                player,
+            );
+        });
+}
+
+pub fn show_world_map(
+    mut egui_context: EguiContexts,
+    mut app_state: ResMut<GameInfo>,
+    memory: Res<MapMemory>,
+    player: Query<&Transform, With<Player>>,
+) {
+    if !app_state.show_world_map {
+        return;
+    }
+    let player = match player.get_single() {
+        Ok(p) => p.translation.truncate(),
+        _ => return,
+    };
+
+    // We display all explored chunks.
+    let mut explored = Rect::from_center_size(player, Vec2::splat(MAP_SIZE));
+    for chunk in memory.explored.iter() {
+        explored = explored.union(chunk.rect());
+    }
+    let scale = WORLD_MAP_SIZE / explored.width().max(explored.height());
+
+    egui::Window::new("World map")
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut app_state.show_world_map)
+        .show(egui_context.ctx_mut(), |ui| {
+            let (response, painter) =
+                ui.allocate_painter(egui::Vec2::splat(WORLD_MAP_SIZE), egui::Sense::hover());
+            draw_map(
+                &painter,
+                response.rect,
+                explored.center(),
+                scale,
+                &memory,
+                player,
+            );
+        });
+}
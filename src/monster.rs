@@ -1,149 +1,252 @@
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy_prototype_lyon::draw;
 use bevy_prototype_lyon::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
 
 use crate::character::{
     Character, CharacterAnimationInfo, CharacterAnimationType, CharacterHealthBar,
     CharacterHealthBarInner, CharacterInfo, CharacterKind, CharacterPoints, GrassEffectBundle,
 };
-use crate::game::OutsideWorld;
+use crate::loot::LootTable;
+use crate::monster_ai::{MonsterAi, MonsterBehaviour};
+
+/// The file describing every kind of monster (relative to the `assets` folder).
+pub const MONSTERS_FILE: &str = "monsters.ron";
 
 #[derive(Component)]
-pub struct Skeleton;
+pub struct Monster(pub MonsterKind);
 
-const WIDTH: f32 = 26.;
-const HEIGHT: f32 = 26.;
+/// Space kept for a monster when placing it on the map.
+pub const MONSTER_SIZE: f32 = 32.;
 
-pub fn spawn_monsters(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
-) {
-    const NB_ANIMATIONS: usize = 3;
-    const ANIMATION_TIME: f32 = 0.15;
-
-    let skeleton_texture = asset_server.load("textures/skeleton.png");
-    let texture_atlas =
-        TextureAtlasLayout::from_grid(UVec2::new(48, 48), NB_ANIMATIONS as _, 4, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    let level = 2;
-
-    commands
-        .spawn((
-            Skeleton,
-            crate::inventory::Inventory {
-                items: Vec::new(),
-                gold: 1, // To be computed based on the monster level, etc.
-                equipped_weapon: None,
-            },
-            Character::new(
-                level,
-                0,
-                // FIXME, should have a method for a specific level.
-                CharacterPoints::level_1(),
-                WIDTH,
-                HEIGHT,
-                CharacterKind::Monster,
-            ),
-            CharacterAnimationInfo::new(
-                ANIMATION_TIME,
-                NB_ANIMATIONS,
-                CharacterAnimationType::ForwardIdle,
-            ),
-            TextureAtlas {
-                layout: texture_atlas_handle,
-                ..default()
-            },
-            SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(Vec2 {
-                        x: WIDTH,
-                        y: HEIGHT,
-                    }),
-                    ..default()
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum MonsterKind {
+    Skeleton,
+}
+
+/// A collider of the monster, relative to its center.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct MonsterCollider {
+    pub half_width: f32,
+    pub half_height: f32,
+    pub offset_y: f32,
+}
+
+impl MonsterCollider {
+    fn collider(&self) -> (Collider, TransformBundle) {
+        (
+            Collider::cuboid(self.half_width, self.half_height),
+            TransformBundle::from(Transform::from_xyz(0.0, self.offset_y, 0.0)),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MonsterLoot {
+    /// Gold dropped for each level of the monster.
+    pub gold_per_level: u32,
+    /// Dropped on top of the gold.
+    pub table: LootTable,
+}
+
+/// Everything needed to spawn a kind of monster.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MonsterDefinition {
+    pub name: String,
+    pub texture: String,
+    /// Size (in pixels) of a frame in the sprite sheet.
+    pub frame_width: u32,
+    pub frame_height: u32,
+    /// Number of frames of each animation. The sprite sheet has one row per direction.
+    pub nb_animations: usize,
+    pub animation_time: f32,
+    /// Size of the monster in the world.
+    pub width: f32,
+    pub height: f32,
+    /// Used for collisions with the world.
+    pub move_box: MonsterCollider,
+    /// Where the monster can be hit.
+    pub hitbox: MonsterCollider,
+    pub points: CharacterPoints,
+    pub loot: MonsterLoot,
+    pub behaviour: MonsterBehaviour,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct MonsterRegistry {
+    monsters: HashMap<MonsterKind, MonsterDefinition>,
+}
+
+impl MonsterRegistry {
+    pub fn load(path: &str) -> Self {
+        let content = match std::fs::read_to_string(crate::asset_path(path)) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Failed to read monsters file `{}`: {}", path, error);
+                return Self::default();
+            }
+        };
+        match ron::from_str(&content) {
+            Ok(monsters) => Self { monsters },
+            Err(error) => {
+                error!("Failed to parse monsters file `{}`: {}", path, error);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn get(&self, kind: MonsterKind) -> Option<&MonsterDefinition> {
+        self.monsters.get(&kind)
+    }
+}
+
+impl MonsterKind {
+    /// `state` is added to the monster entity so it is despawned with the area it belongs to.
+    pub fn spawn<C: Bundle>(
+        self,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlasLayout>,
+        registry: &MonsterRegistry,
+        x: f32,
+        y: f32,
+        level: u16,
+        state: C,
+    ) {
+        let definition = match registry.get(self) {
+            Some(definition) => definition,
+            None => {
+                error!("No definition for monster `{:?}`", self);
+                return;
+            }
+        };
+        let width = definition.width;
+        let height = definition.height;
+
+        let texture = asset_server.load(&definition.texture);
+        let texture_atlas = TextureAtlasLayout::from_grid(
+            UVec2::new(definition.frame_width, definition.frame_height),
+            definition.nb_animations as _,
+            4,
+            None,
+            None,
+        );
+        let texture_atlas_handle = texture_atlases.add(texture_atlas);
+
+        commands
+            .spawn((
+                Monster(self),
+                definition.loot.table.clone(),
+                crate::inventory::Inventory {
+                    items: Vec::new(),
+                    gold: definition.loot.gold_per_level * level as u32,
+                    equipped_weapon: None,
                 },
-                texture: skeleton_texture,
-                transform: Transform::from_xyz(200.0, 210.0, crate::CHARACTER_Z_INDEX),
-                ..default()
-            },
-            RigidBody::Dynamic,
-            Velocity::zero(),
-            LockedAxes::ROTATION_LOCKED,
-            Damping {
-                linear_damping: 8.,
-                angular_damping: 8.,
-            },
-            OutsideWorld,
-        ))
-        .with_children(|children| {
-            // move box
-            children.spawn((
-                Collider::cuboid(8.0, 7.0),
-                TransformBundle::from(Transform::from_xyz(0.0, -5.0, 0.0)),
-                ActiveEvents::COLLISION_EVENTS,
-                CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
-            ));
-            children.spawn((
-                Collider::cuboid(WIDTH / 2. - 6., HEIGHT / 2. - 1.),
-                TransformBundle::from(Transform::from_xyz(0.0, -2.0, 0.0)),
-                Sensor,
-                CollisionGroups::new(crate::HITBOX, crate::HITBOX),
-            ));
-
-            children.spawn((
-                Text2dBundle {
-                    text: Text::from_section(
-                        &format!("Skeleton lvl. {}", level),
-                        TextStyle {
-                            font: asset_server.load(crate::FONT),
-                            font_size: 10.0,
-                            color: Color::LinearRgba(LinearRgba::WHITE),
-                        },
-                    )
-                    .with_justify(JustifyText::Center),
-                    transform: Transform::from_xyz(0.0, HEIGHT / 2. + 7., 1.),
+                Character::new(
+                    level,
+                    0,
+                    definition.points,
+                    width,
+                    height,
+                    CharacterKind::Monster,
+                ),
+                CharacterAnimationInfo::new(
+                    definition.animation_time,
+                    definition.nb_animations,
+                    CharacterAnimationType::ForwardIdle,
+                ),
+                TextureAtlas {
+                    layout: texture_atlas_handle,
                     ..default()
                 },
-                CharacterInfo,
-            ));
-
-            // The health bar.
-            let shape = shapes::Rectangle {
-                extents: Vec2::new(WIDTH + 2., 5.),
-                ..default()
-            };
-            children.spawn((
-                ShapeBundle {
-                    path: GeometryBuilder::build_as(&shape),
-                    spatial: SpatialBundle {
-                        transform: Transform::from_xyz(0., HEIGHT / 2. + 1., 1.),
-                        visibility: Visibility::Hidden,
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(width, height)),
                         ..default()
                     },
+                    texture,
+                    transform: Transform::from_xyz(x, y, crate::CHARACTER_Z_INDEX),
                     ..default()
                 },
-                draw::Fill::color(Color::LinearRgba(LinearRgba::BLACK)),
-                CharacterHealthBar,
-            ));
-
-            children.spawn((
-                ShapeBundle {
-                    path: GeometryBuilder::build_as(&shape),
-                    spatial: SpatialBundle {
-                        transform: Transform::from_xyz(0., HEIGHT / 2. + 1., 1.1),
-                        visibility: Visibility::Hidden,
+                RigidBody::Dynamic,
+                Velocity::zero(),
+                MonsterAi::new(x, y, definition.behaviour),
+                LockedAxes::ROTATION_LOCKED,
+                Damping {
+                    linear_damping: 8.,
+                    angular_damping: 8.,
+                },
+                state,
+            ))
+            .with_children(|children| {
+                // move box
+                children.spawn((
+                    definition.move_box.collider(),
+                    ActiveEvents::COLLISION_EVENTS,
+                    CollisionGroups::new(crate::OUTSIDE_WORLD, crate::OUTSIDE_WORLD),
+                ));
+                children.spawn((
+                    definition.hitbox.collider(),
+                    Sensor,
+                    CollisionGroups::new(crate::HITBOX, crate::HITBOX),
+                ));
+
+                children.spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            &format!("{} lvl. {}", definition.name, level),
+                            TextStyle {
+                                font: asset_server.load(crate::FONT),
+                                font_size: 10.0,
+                                color: Color::LinearRgba(LinearRgba::WHITE),
+                            },
+                        )
+                        .with_justify(JustifyText::Center),
+                        transform: Transform::from_xyz(0.0, height / 2. + 7., 1.),
                         ..default()
                     },
+                    CharacterInfo,
+                ));
+
+                // The health bar.
+                let shape = shapes::Rectangle {
+                    extents: Vec2::new(width + 2., 5.),
                     ..default()
-                },
-                draw::Fill::color(Color::LinearRgba(LinearRgba::RED)),
-                CharacterHealthBarInner,
-            ));
+                };
+                children.spawn((
+                    ShapeBundle {
+                        path: GeometryBuilder::build_as(&shape),
+                        spatial: SpatialBundle {
+                            transform: Transform::from_xyz(0., height / 2. + 1., 1.),
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    draw::Fill::color(Color::LinearRgba(LinearRgba::BLACK)),
+                    CharacterHealthBar,
+                ));
+
+                children.spawn((
+                    ShapeBundle {
+                        path: GeometryBuilder::build_as(&shape),
+                        spatial: SpatialBundle {
+                            transform: Transform::from_xyz(0., height / 2. + 1., 1.1),
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    draw::Fill::color(Color::LinearRgba(LinearRgba::RED)),
+                    CharacterHealthBarInner,
+                ));
 
-            // The "grass effect" (invisible for the moment).
-            children.spawn(GrassEffectBundle::new(HEIGHT, asset_server));
-        });
+                // The "grass effect" (invisible for the moment).
+                children.spawn(GrassEffectBundle::new(height, asset_server));
+            });
+    }
 }
 
 macro_rules! set_vis {
@@ -180,9 +283,9 @@ pub fn update_character_info(
             if let Ok((mut path, mut transform, mut visibility)) = paths.get_mut(*child) {
                 set_vis!(vis, character, visibility);
                 // We only update the size of the red bar.
-                let new_width =
-                    WIDTH * character.stats.health.value() / character.stats.health.max_value();
-                transform.translation.x = -(WIDTH / 2. - new_width / 2.);
+                let new_width = character.width * character.stats.health.value()
+                    / character.stats.health.max_value();
+                transform.translation.x = -(character.width / 2. - new_width / 2.);
                 *path = ShapePath::build_as(&shapes::Rectangle {
                     extents: Vec2::new(new_width, 3.),
                     ..default()
@@ -0,0 +1,273 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::character::{Character, CharacterAnimationInfo, CharacterAnimationType};
+use crate::death::Dying;
+use crate::navigation::NavGrids;
+use crate::player::Player;
+use crate::status_effect::{StatusEffect, StatusEffects};
+use crate::weapon::{Attacker, CombatEvents, CombatRng};
+
+/// Time (in seconds) between two path computations when chasing.
+const PATH_REFRESH_TIME: f32 = 0.5;
+/// When the monster is this close to a path point, it goes to the next one.
+const PATH_POINT_REACHED: f32 = 4.;
+
+/// How a kind of monster behaves, loaded from `MONSTERS_FILE`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct MonsterBehaviour {
+    /// If the player comes closer than this, the monster starts chasing them.
+    pub detection_radius: f32,
+    /// If the player goes further than this, the monster stops chasing them.
+    pub lose_radius: f32,
+    /// Distance from which the monster can hit the player.
+    pub attack_range: f32,
+    /// Time (in seconds) between two attacks of the monster.
+    pub attack_cooldown: f32,
+    /// Below this percentage of health, the monster runs away from the player.
+    pub flee_health_percent: f32,
+    /// How far from its spawn position the monster wanders.
+    pub wander_radius: f32,
+    /// The monster moves slower when it's only wandering.
+    pub wander_speed_factor: f32,
+    /// Applied to the player when the monster hits them.
+    #[serde(default)]
+    pub attack_effect: Option<StatusEffect>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MonsterState {
+    Idle,
+    Wander { target: Vec2 },
+    Chase,
+    Attack,
+    Flee,
+}
+
+#[derive(Debug, Component)]
+pub struct MonsterAi {
+    pub state: MonsterState,
+    behaviour: MonsterBehaviour,
+    /// Where the monster was spawned. It wanders around it.
+    home: Vec2,
+    /// When it's done, the monster switches between idle and wandering.
+    idle_timer: Timer,
+    attack_timer: Timer,
+    path: Vec<Vec2>,
+    path_timer: Timer,
+}
+
+impl MonsterAi {
+    pub fn new(x: f32, y: f32, behaviour: MonsterBehaviour) -> Self {
+        let mut attack_timer = Timer::from_seconds(behaviour.attack_cooldown, TimerMode::Once);
+        // The first attack can happen right away.
+        attack_timer.tick(attack_timer.duration());
+        Self {
+            state: MonsterState::Idle,
+            behaviour,
+            home: Vec2::new(x, y),
+            idle_timer: Self::random_idle_timer(),
+            attack_timer,
+            path: Vec::new(),
+            path_timer: Timer::from_seconds(PATH_REFRESH_TIME, TimerMode::Once),
+        }
+    }
+
+    fn random_idle_timer() -> Timer {
+        Timer::from_seconds(rand::thread_rng().gen_range(1.5..4.), TimerMode::Once)
+    }
+
+    fn set_state(&mut self, state: MonsterState) {
+        if self.state != state {
+            self.state = state;
+            self.path.clear();
+            self.path_timer.reset();
+            self.idle_timer = Self::random_idle_timer();
+        }
+    }
+
+    fn next_state(&self, pos: Vec2, character: &Character, player: Option<Vec2>) -> MonsterState {
+        if let Some(player) = player {
+            let distance = pos.distance(player);
+            let is_chasing = matches!(
+                self.state,
+                MonsterState::Chase | MonsterState::Attack | MonsterState::Flee
+            );
+            if distance < self.behaviour.detection_radius
+                || (is_chasing && distance < self.behaviour.lose_radius)
+            {
+                return if character.stats.health.pourcent() < self.behaviour.flee_health_percent {
+                    MonsterState::Flee
+                } else if distance < self.behaviour.attack_range {
+                    MonsterState::Attack
+                } else {
+                    MonsterState::Chase
+                };
+            }
+        }
+        match self.state {
+            // The player escaped, we go back home.
+            MonsterState::Chase | MonsterState::Attack | MonsterState::Flee => {
+                MonsterState::Wander { target: self.home }
+            }
+            MonsterState::Idle if self.idle_timer.finished() => {
+                let radius = self.behaviour.wander_radius;
+                let offset = Vec2::new(
+                    rand::thread_rng().gen_range(-radius..radius),
+                    rand::thread_rng().gen_range(-radius..radius),
+                );
+                MonsterState::Wander {
+                    target: self.home + offset,
+                }
+            }
+            MonsterState::Wander { target }
+                if self.idle_timer.finished() || pos.distance(target) < PATH_POINT_REACHED =>
+            {
+                MonsterState::Idle
+            }
+            state => state,
+        }
+    }
+
+    /// Returns the direction to take to go to `target`, going around obstacles if possible.
+    fn direction_to(&mut self, nav_grids: &NavGrids, pos: Vec2, target: Vec2) -> Vec2 {
+        if self.path.is_empty() || self.path_timer.finished() {
+            // If no path was found (no navigation grid in dungeons for example), we go in straight
+            // line.
+            self.path = nav_grids
+                .find_path(pos, target)
+                .unwrap_or_else(|| vec![target]);
+            self.path_timer.reset();
+        }
+        while self.path.len() > 1 && pos.distance(self.path[0]) < PATH_POINT_REACHED {
+            self.path.remove(0);
+        }
+        (self.path[0] - pos).normalize_or_zero()
+    }
+}
+
+fn update_animation(
+    animation: &mut CharacterAnimationInfo,
+    sprite: &mut TextureAtlas,
+    direction: Vec2,
+) {
+    let previous = animation.animation_type;
+    if direction == Vec2::ZERO {
+        animation.animation_type.stop_movement();
+        if previous != animation.animation_type {
+            // Monsters don't have idle sprites, so we use the first one of the move animation.
+            sprite.index = previous.get_index(animation.nb_animations);
+        }
+        return;
+    }
+    let (x_axis, y_axis) = if direction.x.abs() > direction.y.abs() {
+        (direction.x.signum() as i8, 0)
+    } else {
+        (0, direction.y.signum() as i8)
+    };
+    if animation.animation_type.is_idle() || !animation.animation_type.is_equal(x_axis, y_axis) {
+        animation.animation_type.set_move(x_axis, y_axis);
+        sprite.index = animation.animation_type.get_index(animation.nb_animations);
+    }
+}
+
+pub fn update_monster_ai(
+    mut commands: Commands,
+    time: Res<Time>,
+    nav_grids: Res<NavGrids>,
+    mut combat_rng: ResMut<CombatRng>,
+    mut combat_events: CombatEvents,
+    mut player: Query<(Entity, &Transform, &mut Character), With<Player>>,
+    mut monsters: Query<
+        (
+            Entity,
+            &mut MonsterAi,
+            &Transform,
+            &mut Velocity,
+            &mut Character,
+            &mut CharacterAnimationInfo,
+            &mut TextureAtlas,
+            Option<&StatusEffects>,
+        ),
+        (Without<Player>, Without<Dying>),
+    >,
+) {
+    let mut player = player.get_single_mut().ok();
+    // A dead player isn't interesting anymore.
+    let player_pos = player
+        .as_ref()
+        .filter(|(_, _, character)| !character.stats.health.is_empty())
+        .map(|(_, transform, _)| transform.translation.truncate());
+
+    for (
+        monster_id,
+        mut ai,
+        transform,
+        mut velocity,
+        mut character,
+        mut animation,
+        mut sprite,
+        effects,
+    ) in monsters.iter_mut()
+    {
+        ai.idle_timer.tick(time.delta());
+        ai.attack_timer.tick(time.delta());
+        ai.path_timer.tick(time.delta());
+
+        let pos = transform.translation.truncate();
+        let state = ai.next_state(pos, &character, player_pos);
+        ai.set_state(state);
+
+        let speed =
+            character.stats.move_speed * effects.map_or(1., |effects| effects.move_speed_factor());
+        let direction = match (ai.state, player_pos) {
+            (MonsterState::Chase, Some(target)) => {
+                velocity.linvel = ai.direction_to(&nav_grids, pos, target) * speed;
+                velocity.linvel
+            }
+            (MonsterState::Flee, Some(target)) => {
+                velocity.linvel = (pos - target).normalize_or_zero() * speed;
+                velocity.linvel
+            }
+            (MonsterState::Wander { target }, _) => {
+                let speed = speed * ai.behaviour.wander_speed_factor;
+                velocity.linvel = ai.direction_to(&nav_grids, pos, target) * speed;
+                velocity.linvel
+            }
+            (MonsterState::Attack, Some(target)) => {
+                velocity.linvel = Vec2::ZERO;
+                // We still want the monster to face the player.
+                update_animation(
+                    &mut animation,
+                    &mut sprite,
+                    (target - pos).normalize_or_zero(),
+                );
+                animation.animation_type.stop_movement();
+                let is_stunned = effects.map_or(false, |effects| effects.is_stunned());
+                if ai.attack_timer.finished() && !is_stunned {
+                    ai.attack_timer.reset();
+                    if let Some((player_id, _, player_character)) = player.as_mut() {
+                        crate::weapon::deal_damage(
+                            &mut commands,
+                            &mut combat_rng.0,
+                            Attacker::new(&character, character.stats.attack, pos)
+                                .with_effect(ai.behaviour.attack_effect),
+                            monster_id,
+                            *player_id,
+                            player_character,
+                            &mut combat_events,
+                        );
+                    }
+                }
+                continue;
+            }
+            _ => {
+                velocity.linvel = Vec2::ZERO;
+                Vec2::ZERO
+            }
+        };
+        update_animation(&mut animation, &mut sprite, direction);
+    }
+}
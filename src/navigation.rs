@@ -0,0 +1,338 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_rapier2d::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::map::{Chunk, LoadedChunks};
+use crate::MAP_SIZE;
+
+/// Size of a cell of the navigation grid. `MAP_SIZE` must be a multiple of it.
+pub const NAV_CELL_SIZE: f32 = 20.;
+const CELLS_PER_CHUNK: i32 = (MAP_SIZE / NAV_CELL_SIZE) as i32;
+/// Space kept between the center of a walkable cell and the obstacles, so characters don't get
+/// stuck on corners.
+const AGENT_MARGIN: f32 = 6.;
+/// Maximum number of cells visited by a path search before giving up.
+const MAX_SEARCH_NODES: usize = 10_000;
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// Which cells of a chunk are blocked by static colliders.
+struct NavGrid {
+    blocked: Vec<bool>,
+    /// The colliders found while building the grid, so we know it must be rebuilt if one of them
+    /// is removed.
+    colliders: HashSet<Entity>,
+}
+
+/// Navigation grids of the loaded chunks, used to find paths around trees, buildings, etc.
+#[derive(Default, Resource)]
+pub struct NavGrids {
+    grids: HashMap<Chunk, NavGrid>,
+}
+
+fn cell_of(pos: Vec2) -> IVec2 {
+    ((pos + MAP_SIZE / 2.) / NAV_CELL_SIZE).floor().as_ivec2()
+}
+
+fn cell_center(cell: IVec2) -> Vec2 {
+    (cell.as_vec2() + 0.5) * NAV_CELL_SIZE - MAP_SIZE / 2.
+}
+
+fn heuristic(from: IVec2, to: IVec2) -> u32 {
+    let diff = (to - from).abs();
+    let (min, max) = (diff.x.min(diff.y) as u32, diff.x.max(diff.y) as u32);
+    DIAGONAL_COST * min + STRAIGHT_COST * (max - min)
+}
+
+impl NavGrids {
+    /// Instead of querying each cell, the colliders around the chunk are looked up once and the
+    /// cells their bounding box covers are marked as blocked. It's a bit too strict with rotated
+    /// or non-rectangular colliders, but much faster.
+    fn build(rapier_context: &RapierContext, chunk: Chunk) -> NavGrid {
+        let first_cell = IVec2::new(chunk.x, chunk.y) * CELLS_PER_CHUNK;
+        let last_cell = first_cell + IVec2::splat(CELLS_PER_CHUNK - 1);
+        // A cell is blocked if an obstacle is closer than `AGENT_MARGIN` from it.
+        let half_size = NAV_CELL_SIZE / 2. + AGENT_MARGIN;
+        let area = chunk.rect().inflate(half_size);
+        let shape = Collider::cuboid(area.half_size().x, area.half_size().y);
+        let filter = QueryFilter::only_fixed()
+            .exclude_sensors()
+            .groups(CollisionGroups::new(
+                crate::OUTSIDE_WORLD,
+                crate::OUTSIDE_WORLD,
+            ));
+
+        let mut blocked = vec![false; (CELLS_PER_CHUNK * CELLS_PER_CHUNK) as usize];
+        let mut colliders = HashSet::default();
+        rapier_context.intersections_with_shape(area.center(), 0., &shape, filter, |entity| {
+            colliders.insert(entity);
+            let aabb = match rapier_context
+                .entity2collider()
+                .get(&entity)
+                .and_then(|handle| rapier_context.colliders.get(*handle))
+            {
+                Some(collider) => collider.compute_aabb(),
+                None => return true,
+            };
+            let min = Vec2::new(aabb.mins.x, aabb.mins.y);
+            let max = Vec2::new(aabb.maxs.x, aabb.maxs.y);
+            let from = cell_of(min - half_size).max(first_cell);
+            let to = cell_of(max + half_size).min(last_cell);
+            for y in from.y..=to.y {
+                for x in from.x..=to.x {
+                    let center = cell_center(IVec2::new(x, y));
+                    if center.x + half_size > min.x
+                        && center.x - half_size < max.x
+                        && center.y + half_size > min.y
+                        && center.y - half_size < max.y
+                    {
+                        let cell = IVec2::new(x, y) - first_cell;
+                        blocked[(cell.y * CELLS_PER_CHUNK + cell.x) as usize] = true;
+                    }
+                }
+            }
+            true
+        });
+        NavGrid { blocked, colliders }
+    }
+
+    /// Returns `true` if the cell is in a loaded chunk and isn't blocked.
+    fn is_walkable(&self, cell: IVec2) -> bool {
+        let chunk = Chunk::new(
+            cell.x.div_euclid(CELLS_PER_CHUNK),
+            cell.y.div_euclid(CELLS_PER_CHUNK),
+        );
+        let x = cell.x.rem_euclid(CELLS_PER_CHUNK);
+        let y = cell.y.rem_euclid(CELLS_PER_CHUNK);
+        self.grids
+            .get(&chunk)
+            .map(|grid| !grid.blocked[(y * CELLS_PER_CHUNK + x) as usize])
+            .unwrap_or(false)
+    }
+
+    pub fn is_walkable_pos(&self, pos: Vec2) -> bool {
+        self.is_walkable(cell_of(pos))
+    }
+
+    /// Finds a path from `from` to `to` using A*. The returned positions are the centers of the
+    /// cells to go through (the one `from` is in excluded), ending with `to`.
+    ///
+    /// Returns `None` if `to` cannot be reached or if it's too far away.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = cell_of(from);
+        let goal = cell_of(to);
+        if !self.is_walkable(goal) {
+            return None;
+        }
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<IVec2, IVec2> = HashMap::default();
+        let mut costs: HashMap<IVec2, u32> = HashMap::default();
+        let mut closed: HashSet<IVec2> = HashSet::default();
+
+        costs.insert(start, 0);
+        open.push(Reverse((heuristic(start, goal), start.x, start.y)));
+
+        while let Some(Reverse((_, x, y))) = open.pop() {
+            let current = IVec2::new(x, y);
+            if current == goal {
+                let mut path = vec![to];
+                let mut cell = current;
+                while let Some(previous) = came_from.get(&cell) {
+                    if *previous != start {
+                        path.push(cell_center(*previous));
+                    }
+                    cell = *previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if !closed.insert(current) {
+                continue;
+            }
+            if closed.len() > MAX_SEARCH_NODES {
+                return None;
+            }
+            let current_cost = costs[&current];
+
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if dx == 0 && dy == 0 {
+                        continue;
+                    }
+                    let next = current + IVec2::new(dx, dy);
+                    if closed.contains(&next) || !self.is_walkable(next) {
+                        continue;
+                    }
+                    let is_diagonal = dx != 0 && dy != 0;
+                    // We don't cut corners.
+                    if is_diagonal
+                        && (!self.is_walkable(current + IVec2::new(dx, 0))
+                            || !self.is_walkable(current + IVec2::new(0, dy)))
+                    {
+                        continue;
+                    }
+                    let cost = current_cost
+                        + if is_diagonal {
+                            DIAGONAL_COST
+                        } else {
+                            STRAIGHT_COST
+                        };
+                    if costs.get(&next).map(|c| cost < *c).unwrap_or(true) {
+                        costs.insert(next, cost);
+                        came_from.insert(next, current);
+                        open.push(Reverse((cost + heuristic(next, goal), next.x, next.y)));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Returns the chunks whose grid might be changed by the given fixed collider, or nothing if the
+/// collider isn't fixed (characters, weapons, etc) or is a sensor.
+fn chunks_blocked_by(rapier_context: &RapierContext, entity: Entity) -> Vec<Chunk> {
+    let Some(collider) = rapier_context
+        .entity2collider()
+        .get(&entity)
+        .and_then(|handle| rapier_context.colliders.get(*handle))
+    else {
+        return Vec::new();
+    };
+    let is_fixed = collider
+        .parent()
+        .and_then(|handle| rapier_context.bodies.get(handle))
+        .map(|body| body.is_fixed())
+        .unwrap_or(true);
+    if !is_fixed || collider.is_sensor() {
+        return Vec::new();
+    }
+    // Cells are blocked by obstacles up to this far from their center, which might be in another
+    // chunk.
+    let margin = NAV_CELL_SIZE / 2. + AGENT_MARGIN;
+    let aabb = collider.compute_aabb();
+    let from = Chunk::from_pos(aabb.mins.x - margin, aabb.mins.y - margin);
+    let to = Chunk::from_pos(aabb.maxs.x + margin, aabb.maxs.y + margin);
+    (from.y..=to.y)
+        .flat_map(|y| (from.x..=to.x).map(move |x| Chunk::new(x, y)))
+        .collect()
+}
+
+/// Builds the navigation grid of the chunks which were just spawned, removes the ones of the
+/// chunks which were despawned and rebuilds the ones where fixed colliders were added or removed.
+///
+/// It must run after rapier wrote back its data so the colliders of the new chunks are known.
+pub fn update_nav_grids(
+    mut nav_grids: ResMut<NavGrids>,
+    loaded_chunks: Res<LoadedChunks>,
+    rapier_context: Res<RapierContext>,
+    added_colliders: Query<Entity, Added<Collider>>,
+    mut removed_colliders: RemovedComponents<Collider>,
+) {
+    nav_grids
+        .grids
+        .retain(|chunk, _| loaded_chunks.contains(*chunk));
+
+    let mut outdated: HashSet<Chunk> = added_colliders
+        .iter()
+        .flat_map(|entity| chunks_blocked_by(&rapier_context, entity))
+        .collect();
+    for entity in removed_colliders.read() {
+        outdated.extend(
+            nav_grids
+                .grids
+                .iter()
+                .filter(|(_, grid)| grid.colliders.contains(&entity))
+                .map(|(chunk, _)| *chunk),
+        );
+    }
+    for chunk in outdated {
+        if nav_grids.grids.contains_key(&chunk) {
+            let grid = NavGrids::build(&rapier_context, chunk);
+            nav_grids.grids.insert(chunk, grid);
+        }
+    }
+
+    for chunk in loaded_chunks.iter() {
+        if !nav_grids.grids.contains_key(&chunk) {
+            let grid = NavGrids::build(&rapier_context, chunk);
+            nav_grids.grids.insert(chunk, grid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns navigation grids with only the origin chunk loaded, with the given cells (relative
+    /// to the chunk) blocked.
+    fn grids_with_blocked(cells: &[IVec2]) -> NavGrids {
+        let mut blocked = vec![false; (CELLS_PER_CHUNK * CELLS_PER_CHUNK) as usize];
+        for cell in cells {
+            blocked[(cell.y * CELLS_PER_CHUNK + cell.x) as usize] = true;
+        }
+        let mut grids = NavGrids::default();
+        grids.grids.insert(
+            Chunk::ORIGIN,
+            NavGrid {
+                blocked,
+                colliders: HashSet::default(),
+            },
+        );
+        grids
+    }
+
+    #[test]
+    fn straight_path() {
+        let grids = grids_with_blocked(&[]);
+        let to = cell_center(IVec2::new(5, 2));
+        let path = grids.find_path(cell_center(IVec2::new(2, 2)), to).unwrap();
+        assert_eq!(
+            path,
+            vec![
+                cell_center(IVec2::new(3, 2)),
+                cell_center(IVec2::new(4, 2)),
+                to
+            ]
+        );
+    }
+
+    #[test]
+    fn path_goes_around_walls() {
+        // A vertical wall between the start and the goal.
+        let wall: Vec<_> = (0..6).map(|y| IVec2::new(4, y)).collect();
+        let grids = grids_with_blocked(&wall);
+        let path = grids
+            .find_path(cell_center(IVec2::new(2, 2)), cell_center(IVec2::new(6, 2)))
+            .unwrap();
+        assert!(path.iter().all(|pos| grids.is_walkable_pos(*pos)));
+        assert!(path.iter().any(|pos| cell_of(*pos).y >= 6));
+    }
+
+    #[test]
+    fn no_path_to_blocked_or_unloaded_cells() {
+        let grids = grids_with_blocked(&[IVec2::new(6, 2)]);
+        let from = cell_center(IVec2::new(2, 2));
+        assert_eq!(grids.find_path(from, cell_center(IVec2::new(6, 2))), None);
+        assert_eq!(grids.find_path(from, Vec2::splat(MAP_SIZE * 2.)), None);
+    }
+
+    #[test]
+    fn no_path_into_enclosed_area() {
+        // The goal is surrounded by blocked cells.
+        let goal = IVec2::new(10, 10);
+        let walls: Vec<_> = (-1..=1)
+            .flat_map(|y| (-1..=1).map(move |x| goal + IVec2::new(x, y)))
+            .filter(|cell| *cell != goal)
+            .collect();
+        let grids = grids_with_blocked(&walls);
+        assert_eq!(
+            grids.find_path(cell_center(IVec2::new(2, 2)), cell_center(goal)),
+            None
+        );
+    }
+}
@@ -5,8 +5,11 @@ use crate::character::{
     Character, CharacterAnimationInfo, CharacterAnimationType, CharacterKind, CharacterPoints,
     GrassEffectBundle,
 };
+use crate::death::Dying;
 use crate::inventory::Inventory;
-use crate::weapon::Weapon;
+use crate::projectile::{spawn_projectile, Projectile};
+use crate::status_effect::StatusEffects;
+use crate::weapon::{spawn_notification, Attacker, Weapon};
 use crate::RUN_STAMINA_CONSUMPTION_PER_SEC;
 
 #[derive(Component)]
@@ -16,6 +19,8 @@ const PLAYER_WIDTH: u32 = 22;
 const PLAYER_HEIGHT: u32 = 24;
 const PLAYER_WIDTH_F: f32 = PLAYER_WIDTH as f32;
 const PLAYER_HEIGHT_F: f32 = PLAYER_HEIGHT as f32;
+const RANGED_PROJECTILE_RADIUS: f32 = 2.;
+const RANGED_PROJECTILE_COLOR: LinearRgba = LinearRgba::rgb(0.75, 0.75, 0.7);
 
 #[derive(Debug, Component)]
 pub struct Player {
@@ -47,7 +52,6 @@ pub fn spawn_player(
         None,
     );
     let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    let weapon_handle = asset_server.load("textures/weapon.png");
     let mut character = Character::new(
         1,
         0,
@@ -57,10 +61,7 @@ pub fn spawn_player(
         CharacterKind::Player,
     );
 
-    const WEAPON_WIDTH: f32 = 7.;
-    const WEAPON_HEIGHT: f32 = 20.;
-
-    let weapon = Weapon::new(1, 1., WEAPON_WIDTH, WEAPON_HEIGHT);
+    let weapon = Weapon::sword(1);
     character.set_weapon(&weapon);
 
     commands
@@ -97,6 +98,7 @@ pub fn spawn_player(
                 },
                 Inventory::new(40, 13, Some(weapon.clone())),
             ),
+            crate::spell::Spellbook::default(),
             RigidBody::Dynamic,
             Velocity::zero(),
             LockedAxes::ROTATION_LOCKED,
@@ -135,16 +137,13 @@ pub fn spawn_player(
             ));
             // The weapon (invisible for the moment).
             children.spawn((
-                weapon,
+                weapon.clone(),
                 IsPlayer,
                 RigidBody::Dynamic,
                 SpriteBundle {
-                    texture: weapon_handle,
+                    texture: asset_server.load(weapon.kind.texture()),
                     sprite: Sprite {
-                        custom_size: Some(Vec2 {
-                            x: WEAPON_WIDTH,
-                            y: WEAPON_HEIGHT,
-                        }),
+                        custom_size: Some(Vec2::new(weapon.width, weapon.height)),
                         ..default()
                     },
                     // We put the collision handler "outside" of the player to avoid triggering
@@ -157,32 +156,12 @@ pub fn spawn_player(
                     visibility: Visibility::Hidden,
                     ..default()
                 },
-                Collider::polyline(
-                    vec![
-                        Vec2 {
-                            x: WEAPON_WIDTH / 4.,
-                            y: WEAPON_HEIGHT / -6.,
-                        },
-                        Vec2 {
-                            x: WEAPON_WIDTH / 4.,
-                            y: WEAPON_HEIGHT / 2.,
-                        },
-                        Vec2 {
-                            x: WEAPON_WIDTH / -4.,
-                            y: WEAPON_HEIGHT / 2.,
-                        },
-                        Vec2 {
-                            x: WEAPON_WIDTH / -4.,
-                            y: WEAPON_HEIGHT / -6.,
-                        },
-                    ],
-                    None,
-                ),
+                weapon.kind.collider(weapon.width, weapon.height),
                 ActiveEvents::COLLISION_EVENTS,
                 CollisionGroups::new(crate::NOTHING, crate::NOTHING),
             ));
             // The "grass effect" (invisible for the moment).
-            children.spawn(GrassEffectBundle::new(PLAYER_HEIGHT_F, asset_server));
+            children.spawn(GrassEffectBundle::new(PLAYER_HEIGHT_F, &asset_server));
         });
 
     println!("player id: {:?}", app_state.player_id);
@@ -191,17 +170,21 @@ pub fn spawn_player(
 pub fn player_movement_system(
     timer: Res<Time>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut player_info: Query<(
-        Entity,
-        &mut Player,
-        &mut TextureAtlas,
-        &mut Velocity,
-        &mut Character,
-        &mut CharacterAnimationInfo,
-    )>,
+    mut player_info: Query<
+        (
+            Entity,
+            &mut Player,
+            &mut TextureAtlas,
+            &mut Velocity,
+            &mut Character,
+            &mut CharacterAnimationInfo,
+            Option<&StatusEffects>,
+        ),
+        Without<Dying>,
+    >,
     mut player_interaction: Query<(&Parent, &mut Transform), With<crate::character::Interaction>>,
 ) {
-    let (entity, mut player, mut sprite, mut rb_vels, mut character, mut animation) =
+    let (entity, mut player, mut sprite, mut rb_vels, mut character, mut animation, effects) =
         match player_info.get_single_mut() {
             Ok(x) => x,
             _ => return,
@@ -222,7 +205,8 @@ pub fn player_movement_system(
         player.is_running = false;
     }
 
-    let mut speed = character.stats.move_speed;
+    let mut speed =
+        character.stats.move_speed * effects.map_or(1., |effects| effects.move_speed_factor());
     if player.is_running {
         speed *= 2.;
     }
@@ -323,28 +307,64 @@ pub fn player_movement_system(
 }
 
 pub fn player_attack_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
     timer: Res<Time>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut player: Query<(&mut Character, &Inventory, &CharacterAnimationInfo), With<Player>>,
+    mut player: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Character,
+            &mut Inventory,
+            &CharacterAnimationInfo,
+            Option<&StatusEffects>,
+        ),
+        (With<Player>, Without<Dying>),
+    >,
     mut weapon_info: Query<
         (
             &mut Weapon,
             &mut Visibility,
             &mut Transform,
             &mut CollisionGroups,
+            &mut Collider,
+            &mut Sprite,
+            &mut Handle<Image>,
         ),
         With<IsPlayer>,
     >,
 ) {
-    let (ref mut character, inventory, animation_info) = player.single_mut();
-    if inventory.equipped_weapon.is_none() {
-        return;
-    }
-    let (mut weapon, mut visibility, mut transform, mut collision_groups) =
-        match weapon_info.get_single_mut() {
+    let (player_id, player_transform, ref mut character, mut inventory, animation_info, effects) =
+        match player.get_single_mut() {
             Ok(p) => p,
-            Err(_) => return,
+            _ => return,
         };
+    let (
+        mut weapon,
+        mut visibility,
+        mut transform,
+        mut collision_groups,
+        mut collider,
+        mut sprite,
+        mut texture,
+    ) = match weapon_info.get_single_mut() {
+        Ok(p) => p,
+        Err(_) => return,
+    };
+    let equipped = match inventory.equipped_weapon {
+        Some(ref weapon) => weapon.clone(),
+        None => {
+            // The weapon might have been broken (or unequipped) in the middle of a swing.
+            character.is_attacking = false;
+            *visibility = Visibility::Hidden;
+            collision_groups.memberships = crate::NOTHING;
+            collision_groups.filters = crate::NOTHING;
+            return;
+        }
+    };
+    let ranged = equipped.ranged;
+    let is_stunned = effects.map_or(false, |effects| effects.is_stunned());
 
     if character.is_attacking {
         let delta = timer.delta().as_secs_f32();
@@ -353,21 +373,64 @@ pub fn player_attack_system(
             || !character
                 .stats
                 .stamina
-                .subtract(delta * weapon.weight * 10.)
+                .subtract(delta * equipped.weight * 10.)
         {
             character.is_attacking = false;
         }
-    } else if keyboard_input.pressed(KeyCode::Space) {
+    } else if keyboard_input.pressed(KeyCode::Space) && !is_stunned {
         character.is_attacking = character.stats.stamina.value()
-            > weapon.weight * 10. * character.attack_timer.duration().as_secs_f32();
+            > equipped.weight * 10. * character.attack_timer.duration().as_secs_f32();
         if character.is_attacking {
             character.attack_timer.reset();
-            *visibility = Visibility::Inherited;
-            collision_groups.memberships = crate::HITBOX;
-            collision_groups.filters = crate::HITBOX;
+            if let Some(ranged) = ranged {
+                // Ranged weapons don't hit anything themselves, they shoot a projectile instead.
+                if ranged.uses_ammunition && !inventory.consume_collectible(1) {
+                    character.is_attacking = false;
+                    spawn_notification(
+                        &mut commands,
+                        &asset_server,
+                        player_id,
+                        character.height,
+                        "No ammunition",
+                        LinearRgba::WHITE,
+                    );
+                } else {
+                    let direction = animation_info.animation_type.direction();
+                    spawn_projectile(
+                        &mut commands,
+                        player_transform.translation.truncate() + direction * character.width / 2.,
+                        direction * ranged.speed,
+                        RANGED_PROJECTILE_RADIUS,
+                        RANGED_PROJECTILE_COLOR,
+                        Projectile::new(
+                            Attacker::new(
+                                character,
+                                character.stats.attack + equipped.effective_attack(),
+                                player_transform.translation.truncate(),
+                            )
+                            .with_effect(equipped.effect),
+                            player_id,
+                            CharacterKind::Player,
+                            ranged.lifetime,
+                        ),
+                    );
+                }
+            } else {
+                // The swung weapon needs the stats, shape and look of the equipped one.
+                *collider = equipped.kind.collider(equipped.width, equipped.height);
+                sprite.custom_size = Some(Vec2::new(equipped.width, equipped.height));
+                if weapon.kind != equipped.kind {
+                    *texture = asset_server.load(equipped.kind.texture());
+                }
+                *weapon = equipped;
+                *visibility = Visibility::Inherited;
+                collision_groups.memberships = crate::HITBOX;
+                collision_groups.filters = crate::HITBOX;
+            }
         }
     }
-    if !character.is_attacking {
+    // While shooting, the attack timer is only used to wait before the next shot.
+    if !character.is_attacking || ranged.is_some() {
         *visibility = Visibility::Hidden;
         collision_groups.memberships = crate::NOTHING;
         collision_groups.filters = crate::NOTHING;
@@ -375,26 +438,33 @@ pub fn player_attack_system(
     }
     let percent =
         character.attack_timer.elapsed_secs() / character.attack_timer.duration().as_secs_f32();
-    let angle = std::f32::consts::PI / 2. * percent - std::f32::consts::PI / 4.;
+    let arc = weapon.kind.swing_arc();
+    let angle = arc * percent - arc / 2.;
+    let reach = if arc == 0. {
+        // Thrust weapons go forward then come back.
+        weapon.kind.reach() * (std::f32::consts::PI * percent).sin()
+    } else {
+        weapon.kind.reach()
+    };
     transform.rotation = match animation_info.animation_type {
         CharacterAnimationType::ForwardIdle | CharacterAnimationType::ForwardMove => {
-            transform.translation.y = PLAYER_HEIGHT_F / -2. - 8.;
+            transform.translation.y = PLAYER_HEIGHT_F / -2. - 8. - reach;
             transform.translation.x = 5. * percent - 2.;
             Quat::from_rotation_z(std::f32::consts::PI + angle)
         }
         CharacterAnimationType::BackwardIdle | CharacterAnimationType::BackwardMove => {
-            transform.translation.y = PLAYER_HEIGHT_F / 2. + 8.;
+            transform.translation.y = PLAYER_HEIGHT_F / 2. + 8. + reach;
             transform.translation.x = -5. * percent + 3.;
             Quat::from_rotation_z(0. + angle)
         }
         CharacterAnimationType::LeftIdle | CharacterAnimationType::LeftMove => {
             transform.translation.y = -5. * percent + 1.;
-            transform.translation.x = PLAYER_WIDTH_F / -2. - 5.;
+            transform.translation.x = PLAYER_WIDTH_F / -2. - 5. - reach;
             Quat::from_rotation_z(std::f32::consts::PI / 2. + angle)
         }
         CharacterAnimationType::RightIdle | CharacterAnimationType::RightMove => {
             transform.translation.y = 5. * percent;
-            transform.translation.x = PLAYER_WIDTH_F / 2. + 5.;
+            transform.translation.x = PLAYER_WIDTH_F / 2. + 5. + reach;
             Quat::from_rotation_z(std::f32::consts::PI / -2. + angle)
         }
     };
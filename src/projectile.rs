@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::draw;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::character::CharacterKind;
+use crate::weapon::Attacker;
+
+/// Something flying in a straight line until it hits a character, a wall or until its lifetime is
+/// over. Hits are handled in `weapon::handle_attacks`, like melee attacks.
+#[derive(Debug, Component)]
+pub struct Projectile {
+    pub attacker: Attacker,
+    pub shooter: Entity,
+    /// The projectile doesn't hurt characters of the same kind.
+    pub shooter_kind: CharacterKind,
+    pub lifetime: Timer,
+}
+
+impl Projectile {
+    /// `lifetime` is in seconds.
+    pub fn new(
+        attacker: Attacker,
+        shooter: Entity,
+        shooter_kind: CharacterKind,
+        lifetime: f32,
+    ) -> Self {
+        Self {
+            attacker,
+            shooter,
+            shooter_kind,
+            lifetime: Timer::from_seconds(lifetime, TimerMode::Once),
+        }
+    }
+}
+
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    pos: Vec2,
+    velocity: Vec2,
+    radius: f32,
+    color: LinearRgba,
+    projectile: Projectile,
+) {
+    let shape = shapes::Circle {
+        radius,
+        ..default()
+    };
+    commands.spawn((
+        ShapeBundle {
+            path: GeometryBuilder::build_as(&shape),
+            spatial: SpatialBundle {
+                transform: Transform::from_xyz(pos.x, pos.y, crate::CHARACTER_Z_INDEX + 0.1),
+                ..default()
+            },
+            ..default()
+        },
+        draw::Fill::color(Color::LinearRgba(color)),
+        projectile,
+        RigidBody::KinematicVelocityBased,
+        Velocity::linear(velocity),
+        Collider::ball(radius),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        CollisionGroups::new(crate::HITBOX, crate::HITBOX),
+    ));
+}
+
+/// Despawns the projectiles which are too old or which ran into a wall.
+pub fn update_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut projectiles: Query<(Entity, &mut Projectile, &Transform)>,
+) {
+    let filter = QueryFilter::only_fixed()
+        .exclude_sensors()
+        .groups(CollisionGroups::new(
+            crate::OUTSIDE_WORLD,
+            crate::OUTSIDE_WORLD,
+        ));
+
+    for (entity, mut projectile, transform) in projectiles.iter_mut() {
+        if projectile.lifetime.tick(time.delta()).finished()
+            || rapier_context
+                .intersection_with_point(transform.translation.truncate(), filter)
+                .is_some()
+        {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::Rng;
+
+use crate::game::OutsideWorld;
+use crate::map::{find_free_spot, Chunk};
+use crate::monster::{MonsterKind, MonsterRegistry, MONSTER_SIZE};
+use crate::navigation::NavGrids;
+use crate::world_clock::WorldClock;
+
+/// Every `LEVEL_DISTANCE` from the origin, monsters gain a level.
+const LEVEL_DISTANCE: f32 = 1_000.;
+/// Time (in seconds) before a killed monster is replaced.
+const RESPAWN_TIME: f32 = 30.;
+/// By how much the population of a spawner is multiplied at night.
+const NIGHT_MONSTERS_FACTOR: usize = 2;
+/// How many times we try to find a walkable position when respawning a monster.
+const MAX_RESPAWN_ATTEMPTS: usize = 10;
+/// Smaller spawners wouldn't have room for a single monster.
+pub const MIN_SPAWNER_RADIUS: f32 = MONSTER_SIZE;
+
+/// Returns the level of the monsters spawned at `pos`: the further from the origin, the harder.
+pub fn level_for_pos(pos: Vec2) -> u16 {
+    1 + (pos.length() / LEVEL_DISTANCE) as u16
+}
+
+/// Keeps up to `max_monsters` monsters of the given kind alive around it.
+#[derive(Debug, Component)]
+pub struct MonsterSpawner {
+    pub kind: MonsterKind,
+    pub max_monsters: usize,
+    pub radius: f32,
+    pub level: u16,
+    respawn_timer: Timer,
+}
+
+/// Added on the monsters created by a `MonsterSpawner` so it knows how many of them are still
+/// alive.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct SpawnedBy(pub Entity);
+
+impl MonsterSpawner {
+    fn max_monsters(&self, is_night: bool) -> usize {
+        if is_night {
+            self.max_monsters * NIGHT_MONSTERS_FACTOR
+        } else {
+            self.max_monsters
+        }
+    }
+
+    fn area(&self, pos: Vec2) -> Rect {
+        Rect::from_center_size(pos, Vec2::splat(self.radius * 2.))
+    }
+}
+
+/// Spawns a spawner at the given position alongside its initial population. `rng` is the one of
+/// the chunk so the monsters are always placed the same way.
+pub fn insert_spawner(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlasLayout>,
+    registry: &MonsterRegistry,
+    rng: &mut crate::SeedType,
+    obstacles: &mut Vec<Rect>,
+    kind: MonsterKind,
+    pos: Vec2,
+    max_monsters: usize,
+    radius: f32,
+    is_night: bool,
+    chunk: Chunk,
+) {
+    let spawner = MonsterSpawner {
+        kind,
+        max_monsters,
+        radius,
+        level: level_for_pos(pos),
+        respawn_timer: Timer::from_seconds(RESPAWN_TIME, TimerMode::Once),
+    };
+    // Monsters shouldn't be spawned outside of the chunk, otherwise they would be despawned
+    // alongside the wrong one.
+    let area = spawner.area(pos).intersect(chunk.rect());
+    let nb_monsters = spawner.max_monsters(is_night);
+    let level = spawner.level;
+    let spawner_id = commands
+        .spawn((
+            spawner,
+            TransformBundle::from(Transform::from_xyz(pos.x, pos.y, 0.)),
+            OutsideWorld,
+            chunk,
+        ))
+        .id();
+
+    for _ in 0..nb_monsters {
+        if let Some(pos) = find_free_spot(rng, area, Vec2::splat(MONSTER_SIZE), obstacles) {
+            kind.spawn(
+                commands,
+                asset_server,
+                texture_atlases,
+                registry,
+                pos.x,
+                pos.y,
+                level,
+                (OutsideWorld, chunk, SpawnedBy(spawner_id)),
+            );
+        }
+    }
+}
+
+/// Replaces the monsters killed around the spawners once their respawn timer is done.
+pub fn update_spawners(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    registry: Res<MonsterRegistry>,
+    time: Res<Time>,
+    clock: Res<WorldClock>,
+    nav_grids: Res<NavGrids>,
+    mut spawners: Query<(Entity, &mut MonsterSpawner, &Transform, &Chunk)>,
+    monsters: Query<&SpawnedBy>,
+) {
+    let mut alive: HashMap<Entity, usize> = HashMap::default();
+    for spawned_by in monsters.iter() {
+        *alive.entry(spawned_by.0).or_default() += 1;
+    }
+
+    let mut rng = rand::thread_rng();
+    for (entity, mut spawner, transform, chunk) in spawners.iter_mut() {
+        let nb_alive = alive.get(&entity).copied().unwrap_or(0);
+        if nb_alive >= spawner.max_monsters(clock.is_night()) {
+            spawner.respawn_timer.reset();
+            continue;
+        }
+        if !spawner.respawn_timer.tick(time.delta()).finished() {
+            continue;
+        }
+        spawner.respawn_timer.reset();
+
+        let center = transform.translation.truncate();
+        let area = spawner.area(center).intersect(chunk.rect());
+        // The spawner might barely overlap its chunk.
+        if area.width() < MONSTER_SIZE || area.height() < MONSTER_SIZE {
+            continue;
+        }
+        let pos = (0..MAX_RESPAWN_ATTEMPTS)
+            .map(|_| {
+                Vec2::new(
+                    rng.gen_range(area.min.x..area.max.x),
+                    rng.gen_range(area.min.y..area.max.y),
+                )
+            })
+            .find(|pos| nav_grids.is_walkable_pos(*pos));
+        if let Some(pos) = pos {
+            spawner.kind.spawn(
+                &mut commands,
+                &asset_server,
+                &mut texture_atlases,
+                &registry,
+                pos.x,
+                pos.y,
+                spawner.level,
+                (OutsideWorld, *chunk, SpawnedBy(entity)),
+            );
+        }
+    }
+}
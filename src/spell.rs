@@ -0,0 +1,270 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::draw;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::rapier::parry::query::ShapeCastOptions;
+
+use crate::character::{Character, CharacterAnimationInfo, CharacterKind};
+use crate::death::Dying;
+use crate::player::Player;
+use crate::projectile::{spawn_projectile, Projectile};
+use crate::status_effect::{StatusEffect, StatusEffects};
+use crate::weapon::{spawn_notification, Attacker};
+
+const FIREBALL_SPEED: f32 = 250.;
+const FIREBALL_RANGE: f32 = 300.;
+const FIREBALL_RADIUS: f32 = 4.;
+/// The fireball damage is `magical_attack` multiplied by this.
+const FIREBALL_DAMAGE_FACTOR: u32 = 2;
+const FIREBALL_COLOR: LinearRgba = LinearRgba::rgb(1., 0.45, 0.1);
+/// The fireball sets its target on fire for this long (in seconds)...
+const FIREBALL_BURN_TIME: f32 = 3.;
+/// ... making it lose `magical_attack` divided by this every second.
+const FIREBALL_BURN_DIVISOR: f32 = 2.;
+const HEAL_RADIUS: f32 = 60.;
+/// The heal amount is `magical_attack` multiplied by this.
+const HEAL_FACTOR: f32 = 3.;
+const HEAL_COLOR: LinearRgba = LinearRgba::rgb(0.3, 1., 0.4);
+/// How long (in seconds) the heal area remains visible.
+const HEAL_EFFECT_TIME: f32 = 0.3;
+const BLINK_DISTANCE: f32 = 80.;
+const NOT_ENOUGH_MANA_COLOR: LinearRgba = LinearRgba::rgb(0.4, 0.6, 1.);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spell {
+    Fireball = 0,
+    Heal = 1,
+    Blink = 2,
+}
+
+impl Spell {
+    pub const ALL: [Self; 3] = [Self::Fireball, Self::Heal, Self::Blink];
+
+    pub fn key(self) -> KeyCode {
+        match self {
+            Self::Fireball => KeyCode::Digit1,
+            Self::Heal => KeyCode::Digit2,
+            Self::Blink => KeyCode::Digit3,
+        }
+    }
+
+    pub fn mana_cost(self) -> f32 {
+        match self {
+            Self::Fireball => 5.,
+            Self::Heal => 12.,
+            Self::Blink => 8.,
+        }
+    }
+
+    /// Time (in seconds) before the spell can be cast again.
+    pub fn cooldown(self) -> f32 {
+        match self {
+            Self::Fireball => 0.6,
+            Self::Heal => 8.,
+            Self::Blink => 4.,
+        }
+    }
+}
+
+/// The spells cooldowns of a character.
+#[derive(Debug, Component)]
+pub struct Spellbook {
+    cooldowns: [Timer; Spell::ALL.len()],
+}
+
+impl Default for Spellbook {
+    fn default() -> Self {
+        Self {
+            cooldowns: Spell::ALL.map(|spell| {
+                let mut timer = Timer::from_seconds(spell.cooldown(), TimerMode::Once);
+                // Spells are available right away.
+                timer.tick(timer.duration());
+                timer
+            }),
+        }
+    }
+}
+
+/// Despawned once its timer is done.
+#[derive(Component)]
+pub struct SpellEffect {
+    timer: Timer,
+}
+
+/// Returns the position the player reaches by going `BLINK_DISTANCE` toward `direction`, stopping
+/// where its "move" box first hits an obstacle so walls can't be crossed.
+fn blink_destination(rapier_context: &RapierContext, pos: Vec2, direction: Vec2) -> Vec2 {
+    let shape = Collider::cuboid(8., 7.);
+    let filter = QueryFilter::only_fixed()
+        .exclude_sensors()
+        .groups(CollisionGroups::new(
+            crate::OUTSIDE_WORLD,
+            crate::OUTSIDE_WORLD,
+        ));
+    let options = ShapeCastOptions {
+        // The shape moves by its whole velocity when the time of impact is 1.
+        max_time_of_impact: 1.,
+        target_distance: 0.,
+        // The player might already touch a wall it's blinking away from.
+        stop_at_penetration: false,
+        compute_impact_geometry_on_penetration: false,
+    };
+    // The "move" box is below the center of the player.
+    let distance = match rapier_context.cast_shape(
+        pos - Vec2::new(0., 5.),
+        0.,
+        direction * BLINK_DISTANCE,
+        &shape,
+        options,
+        filter,
+    ) {
+        Some((_, hit)) => BLINK_DISTANCE * hit.time_of_impact,
+        None => BLINK_DISTANCE,
+    };
+    pos + direction * distance
+}
+
+pub fn cast_spells(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    rapier_context: Res<RapierContext>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Character,
+            &CharacterAnimationInfo,
+            &mut Spellbook,
+            Option<&StatusEffects>,
+        ),
+        (With<Player>, Without<Dying>),
+    >,
+    mut allies: Query<(Entity, &Transform, &mut Character), (Without<Player>, Without<Dying>)>,
+) {
+    let (player_id, mut transform, mut character, animation, mut spellbook, effects) =
+        match player.get_single_mut() {
+            Ok(p) => p,
+            _ => return,
+        };
+    for cooldown in spellbook.cooldowns.iter_mut() {
+        cooldown.tick(time.delta());
+    }
+    if effects.map_or(false, |effects| effects.is_stunned()) {
+        return;
+    }
+
+    for spell in Spell::ALL {
+        if !keyboard_input.just_pressed(spell.key())
+            || !spellbook.cooldowns[spell as usize].finished()
+        {
+            continue;
+        }
+        if character.stats.mana.value() < spell.mana_cost() {
+            spawn_notification(
+                &mut commands,
+                &asset_server,
+                player_id,
+                character.height,
+                "Not enough mana",
+                NOT_ENOUGH_MANA_COLOR,
+            );
+            continue;
+        }
+        character.stats.mana.subtract(spell.mana_cost());
+        spellbook.cooldowns[spell as usize].reset();
+
+        let pos = transform.translation.truncate();
+        let direction = animation.animation_type.direction();
+        match spell {
+            Spell::Fireball => {
+                let attack = character.stats.magical_attack * FIREBALL_DAMAGE_FACTOR;
+                let burning = StatusEffect::burning(
+                    character.stats.magical_attack as f32 / FIREBALL_BURN_DIVISOR,
+                    FIREBALL_BURN_TIME,
+                );
+                spawn_projectile(
+                    &mut commands,
+                    pos + direction * character.width / 2.,
+                    direction * FIREBALL_SPEED,
+                    FIREBALL_RADIUS,
+                    FIREBALL_COLOR,
+                    Projectile::new(
+                        Attacker::magical(&character, attack, pos).with_effect(Some(burning)),
+                        player_id,
+                        CharacterKind::Player,
+                        FIREBALL_RANGE / FIREBALL_SPEED,
+                    ),
+                );
+            }
+            Spell::Heal => {
+                let amount = character.stats.magical_attack as f32 * HEAL_FACTOR;
+                let text = format!("+{:.0}", amount);
+                character.stats.health.add(amount);
+                spawn_notification(
+                    &mut commands,
+                    &asset_server,
+                    player_id,
+                    character.height,
+                    &text,
+                    HEAL_COLOR,
+                );
+                // Humans around the player are healed as well.
+                for (entity, ally_transform, mut ally) in allies.iter_mut() {
+                    if ally.kind == CharacterKind::Human
+                        && ally_transform.translation.truncate().distance(pos) < HEAL_RADIUS
+                    {
+                        ally.stats.health.add(amount);
+                        spawn_notification(
+                            &mut commands,
+                            &asset_server,
+                            entity,
+                            ally.height,
+                            &text,
+                            HEAL_COLOR,
+                        );
+                    }
+                }
+                let shape = shapes::Circle {
+                    radius: HEAL_RADIUS,
+                    ..default()
+                };
+                let effect = commands
+                    .spawn((
+                        ShapeBundle {
+                            path: GeometryBuilder::build_as(&shape),
+                            spatial: SpatialBundle {
+                                transform: Transform::from_xyz(0., 0., -0.1),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        draw::Fill::color(Color::LinearRgba(HEAL_COLOR.with_alpha(0.25))),
+                        SpellEffect {
+                            timer: Timer::from_seconds(HEAL_EFFECT_TIME, TimerMode::Once),
+                        },
+                    ))
+                    .id();
+                commands.entity(player_id).add_child(effect);
+            }
+            Spell::Blink => {
+                let destination = blink_destination(&rapier_context, pos, direction);
+                transform.translation.x = destination.x;
+                transform.translation.y = destination.y;
+            }
+        }
+    }
+}
+
+pub fn update_spell_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut effects: Query<(Entity, &mut SpellEffect)>,
+) {
+    for (entity, mut effect) in effects.iter_mut() {
+        if effect.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+
+use crate::player::Player;
+use crate::weapon::{AttackLanded, AttackOutcome, CharacterDied, DamageDealt, GrassCut};
+
+/// What the player did (or endured) in fights since the game started.
+#[derive(Debug, Default, Resource)]
+pub struct CombatStatistics {
+    pub kills: u32,
+    pub deaths: u32,
+    pub damage_dealt: u64,
+    pub damage_taken: u64,
+    pub critical_hits: u32,
+    pub dodged_attacks: u32,
+    pub grass_cut: u32,
+}
+
+pub fn update_combat_statistics(
+    mut statistics: ResMut<CombatStatistics>,
+    mut attacks: EventReader<AttackLanded>,
+    mut damages: EventReader<DamageDealt>,
+    mut deaths: EventReader<CharacterDied>,
+    mut grass_cuts: EventReader<GrassCut>,
+    player: Query<Entity, With<Player>>,
+) {
+    let player = match player.get_single() {
+        Ok(player) => player,
+        _ => return,
+    };
+    for attack in attacks.read() {
+        match attack.outcome {
+            AttackOutcome::Critical(_) if attack.attacker == player => {
+                statistics.critical_hits += 1
+            }
+            AttackOutcome::Miss if attack.receiver == player => statistics.dodged_attacks += 1,
+            _ => {}
+        }
+    }
+    for damage in damages.read() {
+        if damage.attacker == player {
+            statistics.damage_dealt += damage.damage as u64;
+        } else if damage.receiver == player {
+            statistics.damage_taken += damage.damage as u64;
+        }
+    }
+    for death in deaths.read() {
+        if death.entity == player {
+            statistics.deaths += 1;
+        } else if death.killer == Some(player) {
+            statistics.kills += 1;
+        }
+    }
+    for grass_cut in grass_cuts.read() {
+        if grass_cut.cutter == player {
+            statistics.grass_cut += 1;
+        }
+    }
+}
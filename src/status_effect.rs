@@ -0,0 +1,292 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::draw;
+use bevy_prototype_lyon::prelude::*;
+use serde::Deserialize;
+
+use crate::character::Character;
+use crate::death::Dying;
+use crate::player::Player;
+use crate::weapon::CharacterDied;
+
+const ICON_RADIUS: f32 = 2.;
+const ICON_SPACING: f32 = 6.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum StatusEffectKind {
+    Poison,
+    Burning,
+    Slow,
+    Stun,
+}
+
+impl StatusEffectKind {
+    pub fn color(self) -> LinearRgba {
+        match self {
+            Self::Poison => LinearRgba::rgb(0.4, 0.85, 0.2),
+            Self::Burning => LinearRgba::rgb(1., 0.45, 0.1),
+            Self::Slow => LinearRgba::rgb(0.4, 0.6, 1.),
+            Self::Stun => LinearRgba::rgb(1., 1., 0.4),
+        }
+    }
+
+    /// Letter displayed in the HUD icon.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Poison => "P",
+            Self::Burning => "B",
+            Self::Slow => "S",
+            Self::Stun => "!",
+        }
+    }
+}
+
+/// A timed effect. What `strength` means depends on `kind`:
+///
+/// * `Poison` and `Burning`: health lost per second. Health doesn't regenerate meanwhile.
+/// * `Slow`: `move_speed` is multiplied by it.
+/// * `Stun`: unused, the character can neither move nor attack.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    /// In seconds.
+    pub duration: f32,
+    #[serde(default)]
+    pub strength: f32,
+}
+
+impl StatusEffect {
+    pub fn poison(damage_per_sec: f32, duration: f32) -> Self {
+        Self {
+            kind: StatusEffectKind::Poison,
+            duration,
+            strength: damage_per_sec,
+        }
+    }
+
+    pub fn burning(damage_per_sec: f32, duration: f32) -> Self {
+        Self {
+            kind: StatusEffectKind::Burning,
+            duration,
+            strength: damage_per_sec,
+        }
+    }
+
+    pub fn slow(speed_factor: f32, duration: f32) -> Self {
+        Self {
+            kind: StatusEffectKind::Slow,
+            duration,
+            strength: speed_factor,
+        }
+    }
+
+    pub fn stun(duration: f32) -> Self {
+        Self {
+            kind: StatusEffectKind::Stun,
+            duration,
+            strength: 0.,
+        }
+    }
+
+    fn is_stronger_than(&self, other: &Self) -> bool {
+        match self.kind {
+            // The lower the factor, the slower the character.
+            StatusEffectKind::Slow => self.strength < other.strength,
+            _ => self.strength > other.strength,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ActiveEffect {
+    effect: StatusEffect,
+    timer: Timer,
+}
+
+/// The effects currently affecting a character. It is inserted the first time an effect is applied.
+#[derive(Debug, Default, Component)]
+pub struct StatusEffects {
+    effects: Vec<ActiveEffect>,
+}
+
+impl StatusEffects {
+    /// If an effect of the same kind is already running, the strongest one is kept and the
+    /// longest remaining duration is used.
+    pub fn apply(&mut self, effect: StatusEffect) {
+        match self
+            .effects
+            .iter_mut()
+            .find(|active| active.effect.kind == effect.kind)
+        {
+            Some(active) => {
+                if effect.is_stronger_than(&active.effect) {
+                    active.effect.strength = effect.strength;
+                }
+                let remaining = active.timer.remaining_secs().max(effect.duration);
+                active.timer = Timer::from_seconds(remaining, TimerMode::Once);
+            }
+            None => self.effects.push(ActiveEffect {
+                effect,
+                timer: Timer::from_seconds(effect.duration, TimerMode::Once),
+            }),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    pub fn has(&self, kind: StatusEffectKind) -> bool {
+        self.effects.iter().any(|active| active.effect.kind == kind)
+    }
+
+    pub fn kinds(&self) -> Vec<StatusEffectKind> {
+        self.effects
+            .iter()
+            .map(|active| active.effect.kind)
+            .collect()
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.has(StatusEffectKind::Stun)
+    }
+
+    /// `move_speed` needs to be multiplied by it.
+    pub fn move_speed_factor(&self) -> f32 {
+        if self.is_stunned() {
+            return 0.;
+        }
+        self.effects
+            .iter()
+            .filter(|active| active.effect.kind == StatusEffectKind::Slow)
+            .map(|active| active.effect.strength)
+            .product()
+    }
+
+    /// Returns `true` if the health shouldn't be regenerated.
+    pub fn pauses_regeneration(&self) -> bool {
+        self.has(StatusEffectKind::Poison) || self.has(StatusEffectKind::Burning)
+    }
+
+    fn damage_per_sec(&self) -> f32 {
+        self.effects
+            .iter()
+            .filter(|active| {
+                matches!(
+                    active.effect.kind,
+                    StatusEffectKind::Poison | StatusEffectKind::Burning
+                )
+            })
+            .map(|active| active.effect.strength)
+            .sum()
+    }
+}
+
+/// Applies `effect` to `entity`, inserting its `StatusEffects` if it doesn't have one yet.
+pub fn apply_status_effect(commands: &mut Commands, entity: Entity, effect: StatusEffect) {
+    commands.add(move |world: &mut World| {
+        // The entity might have been despawned in the meantime.
+        let mut entity = match world.get_entity_mut(entity) {
+            Some(entity) => entity,
+            None => return,
+        };
+        match entity.get_mut::<StatusEffects>() {
+            Some(mut effects) => effects.apply(effect),
+            None => {
+                let mut effects = StatusEffects::default();
+                effects.apply(effect);
+                entity.insert(effects);
+            }
+        }
+    });
+}
+
+/// Ticks the effects, removes the damage they deal and removes the ones which are over.
+pub fn update_status_effects(
+    time: Res<Time>,
+    mut deaths: EventWriter<CharacterDied>,
+    mut characters: Query<(Entity, &mut Character, &mut StatusEffects), Without<Dying>>,
+) {
+    for (entity, mut character, mut effects) in characters.iter_mut() {
+        if effects.is_empty() {
+            continue;
+        }
+        let damage = effects.damage_per_sec() * time.delta_seconds();
+        effects
+            .effects
+            .retain_mut(|active| !active.timer.tick(time.delta()).finished());
+        if damage > 0. && !character.stats.health.is_empty() {
+            character.stats.health.subtract(damage);
+            if character.stats.health.is_empty() {
+                deaths.send(CharacterDied {
+                    entity,
+                    killer: None,
+                });
+            }
+        }
+    }
+}
+
+/// Kinds of the effects currently displayed by its children icons.
+#[derive(Debug, Default, Component)]
+pub struct StatusEffectIcons(pub Vec<StatusEffectKind>);
+
+/// Displays the effects of the non-player characters above their health bar.
+pub fn update_status_effect_icons(
+    mut commands: Commands,
+    characters: Query<(Entity, &Character, &StatusEffects, &Children), Without<Player>>,
+    mut icons: Query<&mut StatusEffectIcons>,
+) {
+    for (entity, character, effects, children) in characters.iter() {
+        let kinds = effects.kinds();
+        let holder = match children.iter().find(|child| icons.contains(**child)) {
+            Some(holder) => {
+                let mut displayed = icons.get_mut(*holder).unwrap();
+                if displayed.0 == kinds {
+                    continue;
+                }
+                displayed.0 = kinds.clone();
+                commands.entity(*holder).despawn_descendants();
+                *holder
+            }
+            None => {
+                let holder = commands
+                    .spawn((
+                        SpatialBundle::from_transform(Transform::from_xyz(
+                            0.,
+                            character.height / 2. + 15.,
+                            1.2,
+                        )),
+                        StatusEffectIcons(kinds.clone()),
+                    ))
+                    .id();
+                commands.entity(entity).add_child(holder);
+                holder
+            }
+        };
+        let shape = shapes::Circle {
+            radius: ICON_RADIUS,
+            ..default()
+        };
+        let offset = (kinds.len() as f32 - 1.) * ICON_SPACING / 2.;
+        commands.entity(holder).with_children(|parent| {
+            for (index, kind) in kinds.into_iter().enumerate() {
+                parent.spawn((
+                    ShapeBundle {
+                        path: GeometryBuilder::build_as(&shape),
+                        spatial: SpatialBundle::from_transform(Transform::from_xyz(
+                            index as f32 * ICON_SPACING - offset,
+                            0.,
+                            0.,
+                        )),
+                        ..default()
+                    },
+                    draw::Fill::color(Color::LinearRgba(kind.color())),
+                ));
+            }
+        });
+    }
+}
@@ -1,16 +1,31 @@
 use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
 use bevy_prototype_lyon::draw;
 use bevy_prototype_lyon::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::character::{
     Character, CharacterAnimationInfo, CharacterAnimationType, CharacterBundle, CharacterKind,
-    CharacterPoints,
+    CharacterPoints, InteractionText,
 };
-use crate::inventory::Inventory;
+use crate::death::Dying;
+use crate::inventory::{Inventory, InventoryItem};
+use crate::player::Player;
+use crate::weapon::Weapon;
+use crate::GameInfo;
 
 #[derive(Component)]
-struct Vendor;
+pub struct Vendor {
+    /// Weapon vendors repair weapons.
+    is_weapon_vendor: bool,
+}
+
+/// Where the weapon to repair is.
+#[derive(Clone, Copy)]
+enum WeaponSlot {
+    Equipped,
+    Inventory(usize),
+}
 
 pub fn spawn_vendor<C: Component>(
     commands: &mut Commands,
@@ -36,7 +51,7 @@ pub fn spawn_vendor<C: Component>(
 
     commands
         .spawn((
-            Vendor,
+            Vendor { is_weapon_vendor },
             state,
             CharacterBundle::new(
                 Character::new(
@@ -99,3 +114,121 @@ pub fn spawn_vendor<C: Component>(
             ));
         });
 }
+
+/// Returns `true` if the player can talk to a weapon vendor (which displays the interaction text
+/// in this case).
+fn can_talk_to_weapon_vendor(
+    vendors: &Query<(&Vendor, &Children)>,
+    interaction_texts: &Query<(), With<InteractionText>>,
+) -> bool {
+    vendors.iter().any(|(vendor, children)| {
+        vendor.is_weapon_vendor
+            && children
+                .iter()
+                .any(|child| interaction_texts.contains(*child))
+    })
+}
+
+/// Opens the repair window when the player presses ENTER next to a weapon vendor.
+pub fn talk_to_vendors(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut app_state: ResMut<GameInfo>,
+    vendors: Query<(&Vendor, &Children)>,
+    interaction_texts: Query<(), With<InteractionText>>,
+) {
+    if keyboard_input.just_released(KeyCode::Enter)
+        && can_talk_to_weapon_vendor(&vendors, &interaction_texts)
+    {
+        app_state.show_repair_window = true;
+    }
+}
+
+/// Lists the weapons of the player with what it costs to repair them. The window is closed as soon
+/// as the player walks away from the vendor.
+pub fn show_repair_window(
+    mut egui_context: EguiContexts,
+    mut app_state: ResMut<GameInfo>,
+    mut player: Query<&mut Inventory, (With<Player>, Without<Dying>)>,
+    vendors: Query<(&Vendor, &Children)>,
+    interaction_texts: Query<(), With<InteractionText>>,
+) {
+    if !app_state.show_repair_window {
+        return;
+    }
+    let mut inventory = match player.get_single_mut() {
+        Ok(p) => p,
+        _ => {
+            app_state.show_repair_window = false;
+            return;
+        }
+    };
+    if !can_talk_to_weapon_vendor(&vendors, &interaction_texts) {
+        app_state.show_repair_window = false;
+        return;
+    }
+
+    let mut weapons: Vec<(WeaponSlot, &Weapon)> = Vec::new();
+    if let Some(ref weapon) = inventory.equipped_weapon {
+        weapons.push((WeaponSlot::Equipped, weapon));
+    }
+    for (index, item) in inventory.items.iter().enumerate() {
+        if let Some(InventoryItem::Weapon(weapon)) = item {
+            weapons.push((WeaponSlot::Inventory(index), weapon));
+        }
+    }
+    let gold = inventory.gold;
+    let mut to_repair = None;
+
+    egui::Window::new("Weapon repair")
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut app_state.show_repair_window)
+        .show(egui_context.ctx_mut(), |ui| {
+            if weapons.is_empty() {
+                ui.label("You don't have any weapon.");
+                return;
+            }
+            egui::Grid::new("weapon_repair").show(ui, |ui| {
+                for (slot, weapon) in weapons.iter() {
+                    let name = match slot {
+                        WeaponSlot::Equipped => format!("{} (equipped)", weapon.name()),
+                        WeaponSlot::Inventory(_) => weapon.name().to_owned(),
+                    };
+                    ui.label(&name);
+                    ui.label(&format!(
+                        "{} / {}",
+                        weapon.durability.current, weapon.durability.max
+                    ));
+                    let cost = weapon.repair_cost();
+                    let can_repair = weapon.durability.missing() > 0 && cost <= gold;
+                    if ui
+                        .add_enabled(
+                            can_repair,
+                            egui::Button::new(format!("Repair ({} gold)", cost)),
+                        )
+                        .clicked()
+                    {
+                        to_repair = Some((*slot, cost));
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            ui.label(&format!("Gold: {}", gold));
+        });
+
+    // We do it in two steps to avoid triggering a `Changed` event on the inventory every frame.
+    if let Some((slot, cost)) = to_repair {
+        let weapon = match slot {
+            WeaponSlot::Equipped => inventory.equipped_weapon.as_mut(),
+            WeaponSlot::Inventory(index) => match inventory.items[index] {
+                Some(InventoryItem::Weapon(ref mut weapon)) => Some(weapon),
+                _ => None,
+            },
+        };
+        if let Some(weapon) = weapon {
+            weapon.durability.repair();
+            inventory.gold -= cost;
+        }
+    }
+}
@@ -1,25 +1,316 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy::utils::Duration;
 use bevy_rapier2d::prelude::*;
 use bevy_rapier2d::rapier::geometry::CollisionEventFlags;
+use rand::Rng;
+use rand_seeder::Seeder;
+use serde::Deserialize;
 
 use crate::character::{Character, CharacterKind};
 use crate::environment::Grass;
+use crate::inventory::Inventory;
+use crate::projectile::Projectile;
+use crate::status_effect::{apply_status_effect, StatusEffect};
 
 const NOTIFICATION_MOVE: f32 = 5.;
 const NOTIFICATION_TIME: f32 = 0.5;
+/// Maximum dodge chance (in percent). Against an opponent with similar stats, it's about half of it.
+const MAX_DODGE_CHANCE: f32 = 30.;
+/// Same as `MAX_DODGE_CHANCE` but for critical hits.
+const MAX_CRITICAL_CHANCE: f32 = 25.;
+const CRITICAL_MULTIPLIER: f32 = 1.5;
+const CRITICAL_COLOR: LinearRgba = LinearRgba::rgb(1., 0.85, 0.);
+/// How long (in seconds) a character is pushed back and flashes after taking a hit.
+const HIT_REACTION_TIME: f32 = 0.2;
+/// Speed of the push back right after the hit. It then decreases until `HIT_REACTION_TIME`.
+const KNOCKBACK_SPEED: f32 = 250.;
+/// While flashing, the sprite alternates between its normal color and `FLASH_COLOR` this often.
+const FLASH_BLINK_TIME: f32 = 0.05;
+const FLASH_COLOR: LinearRgba = LinearRgba::rgb(1., 0.3, 0.3);
+const DEFAULT_DURABILITY: u32 = 100;
+/// Below this ratio of its durability, a weapon is worn out and its attack is halved.
+const WORN_OUT_RATIO: f32 = 0.25;
+const WEAR_COLOR: LinearRgba = LinearRgba::rgb(1., 0.6, 0.2);
+/// Repairing this many durability points of a weapon costs its attack (plus one) in gold.
+const REPAIR_POINTS_PER_GOLD: u32 = 10;
+
+/// Random generator used to resolve attacks. It is derived from `SEED` so fights can be replayed.
+#[derive(Resource)]
+pub struct CombatRng(pub crate::SeedType);
+
+impl Default for CombatRng {
+    fn default() -> Self {
+        Self(Seeder::from(&format!("{};combat", crate::SEED)).make_rng())
+    }
+}
+
+/// What is needed from the attacker to resolve an attack.
+#[derive(Debug, Clone, Copy)]
+pub struct Attacker {
+    /// Attack of the character with its weapon.
+    pub attack: u32,
+    pub level: u16,
+    pub critical_attack_chance: u32,
+    pub dexterity: u32,
+    /// Magical attacks are reduced by `magical_defense` instead of `defense`.
+    pub is_magical: bool,
+    /// Applied to the receiver if the attack didn't miss.
+    pub effect: Option<StatusEffect>,
+    /// Where the attack comes from. The receiver is pushed away from it.
+    pub origin: Vec2,
+}
+
+impl Attacker {
+    pub fn new(character: &Character, attack: u32, origin: Vec2) -> Self {
+        Self {
+            attack,
+            level: character.level,
+            critical_attack_chance: character.stats.critical_attack_chance,
+            dexterity: character.points.dexterity,
+            is_magical: false,
+            effect: None,
+            origin,
+        }
+    }
+
+    pub fn magical(character: &Character, attack: u32, origin: Vec2) -> Self {
+        Self {
+            is_magical: true,
+            ..Self::new(character, attack, origin)
+        }
+    }
+
+    pub fn with_effect(self, effect: Option<StatusEffect>) -> Self {
+        Self { effect, ..self }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttackOutcome {
+    Miss,
+    Hit(u32),
+    Critical(u32),
+}
+
+/// Rolls the dodge of `receiver` and the critical hit of `attacker`, then computes the damage.
+pub fn resolve_attack<R: Rng>(
+    rng: &mut R,
+    attacker: Attacker,
+    receiver: &Character,
+) -> AttackOutcome {
+    // The opponent level, dexterity (for dodge) and agility (for critical hits) reduce the chances.
+    let dodge = receiver.stats.dodge_change as f32;
+    let dodge_chance =
+        MAX_DODGE_CHANCE * dodge / (dodge + attacker.level as f32 + attacker.dexterity as f32);
+    if rng.gen_range(0. ..100.) < dodge_chance {
+        return AttackOutcome::Miss;
+    }
+    let defense = if attacker.is_magical {
+        receiver.stats.magical_defense
+    } else {
+        receiver.stats.defense
+    };
+    let damage = attacker.attack.saturating_sub(defense).max(1);
+    let critical = attacker.critical_attack_chance as f32;
+    let critical_chance = MAX_CRITICAL_CHANCE * critical
+        / (critical + receiver.level as f32 + receiver.points.agility as f32);
+    if rng.gen_range(0. ..100.) < critical_chance {
+        AttackOutcome::Critical((damage as f32 * CRITICAL_MULTIPLIER) as u32)
+    } else {
+        AttackOutcome::Hit(damage)
+    }
+}
 
 #[derive(Debug, Component)]
 pub struct Notification {
     pub timer: Timer,
 }
 
-#[derive(Debug, Component, Clone)]
+/// Inserted on a character which just took a hit: it's pushed away from `from` and flashes.
+#[derive(Debug, Component)]
+pub struct HitReaction {
+    from: Vec2,
+    timer: Timer,
+}
+
+/// Sent every time an attack reaches a character, even if it was dodged.
+#[derive(Debug, Event)]
+pub struct AttackLanded {
+    pub attacker: Entity,
+    pub receiver: Entity,
+    pub outcome: AttackOutcome,
+    /// Spells don't wear the attacker weapon.
+    pub is_magical: bool,
+}
+
+/// Sent every time a character loses health because of an attack.
+#[derive(Debug, Event)]
+pub struct DamageDealt {
+    pub attacker: Entity,
+    pub receiver: Entity,
+    pub damage: u32,
+}
+
+/// Sent when the health of a character reaches zero. The character isn't dying yet, it's done by
+/// `death::handle_character_deaths`.
+#[derive(Debug, Event)]
+pub struct CharacterDied {
+    pub entity: Entity,
+    /// `None` if it wasn't killed by an attack (by a status effect for example).
+    pub killer: Option<Entity>,
+}
+
+/// Sent when an attack hits some grass.
+#[derive(Debug, Event)]
+pub struct GrassCut {
+    pub grass: Entity,
+    pub cutter: Entity,
+}
+
+/// Everything that needs to be sent when resolving an attack.
+#[derive(SystemParam)]
+pub struct CombatEvents<'w> {
+    pub attacks: EventWriter<'w, AttackLanded>,
+    pub damages: EventWriter<'w, DamageDealt>,
+    pub deaths: EventWriter<'w, CharacterDied>,
+}
+
+/// Defines how a melee weapon is swung and what part of it hurts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum WeaponKind {
+    Dagger,
+    #[default]
+    Sword,
+    Axe,
+    Spear,
+}
+
+impl WeaponKind {
+    /// Angle (in radians) covered by a swing. Zero means the weapon is thrust forward instead.
+    pub fn swing_arc(self) -> f32 {
+        match self {
+            Self::Dagger => std::f32::consts::PI / 3.,
+            Self::Sword => std::f32::consts::PI / 2.,
+            Self::Axe => std::f32::consts::PI * 2. / 3.,
+            Self::Spear => 0.,
+        }
+    }
+
+    /// How much further from the character than a sword the weapon goes.
+    pub fn reach(self) -> f32 {
+        match self {
+            Self::Dagger => -3.,
+            Self::Sword | Self::Axe => 0.,
+            Self::Spear => 10.,
+        }
+    }
+
+    pub fn texture(self) -> &'static str {
+        match self {
+            Self::Dagger => "textures/dagger.png",
+            Self::Sword => "textures/weapon.png",
+            Self::Axe => "textures/axe.png",
+            Self::Spear => "textures/spear.png",
+        }
+    }
+
+    /// The weapon collider for a weapon of `width` x `height`.
+    pub fn collider(self, width: f32, height: f32) -> Collider {
+        let (left, right, top, bottom) = (width / -4., width / 4., height / 2., height / -6.);
+        let points = match self {
+            Self::Dagger | Self::Sword => {
+                vec![(right, bottom), (right, top), (left, top), (left, bottom)]
+            }
+            // The head of the axe sticks out on one side.
+            Self::Axe => vec![
+                (right, bottom),
+                (right, top / 2.),
+                (right * 3., top / 2.),
+                (right * 3., top),
+                (left, top),
+                (left, bottom),
+            ],
+            // Only the tip of the spear hurts.
+            Self::Spear => vec![
+                (right, top / 2.),
+                (right, top),
+                (left, top),
+                (left, top / 2.),
+            ],
+        };
+        Collider::polyline(
+            points.into_iter().map(|(x, y)| Vec2::new(x, y)).collect(),
+            None,
+        )
+    }
+}
+
+/// What makes a weapon shoot projectiles instead of being swung.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RangedWeapon {
+    /// How far the projectile goes in one second.
+    pub speed: f32,
+    /// Time (in seconds) before the projectile disappears.
+    pub lifetime: f32,
+    /// If `true`, each shot consumes one collectible from the inventory.
+    pub uses_ammunition: bool,
+}
+
+/// Every hit landed with a weapon removes one point. It breaks once `current` reaches zero.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Durability {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Default for Durability {
+    fn default() -> Self {
+        Self::new(DEFAULT_DURABILITY)
+    }
+}
+
+impl Durability {
+    pub fn new(max: u32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn missing(&self) -> u32 {
+        // `current` might be higher than `max` in the definition files.
+        self.max.saturating_sub(self.current)
+    }
+
+    pub fn is_worn_out(&self) -> bool {
+        (self.current as f32) < self.max as f32 * WORN_OUT_RATIO
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.current == 0
+    }
+
+    pub fn wear(&mut self) {
+        self.current = self.current.saturating_sub(1);
+    }
+
+    pub fn repair(&mut self) {
+        self.current = self.max;
+    }
+}
+
+#[derive(Debug, Component, Clone, Deserialize)]
 pub struct Weapon {
     pub attack: u32,
     pub weight: f32,
     pub width: f32,
     pub height: f32,
+    #[serde(default)]
+    pub ranged: Option<RangedWeapon>,
+    /// Applied to the characters it hits.
+    #[serde(default)]
+    pub effect: Option<StatusEffect>,
+    #[serde(default)]
+    pub kind: WeaponKind,
+    #[serde(default)]
+    pub durability: Durability,
 }
 
 impl Weapon {
@@ -29,6 +320,84 @@ impl Weapon {
             weight,
             width,
             height,
+            ranged: None,
+            effect: None,
+            kind: WeaponKind::Sword,
+            durability: Durability::default(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match (self.ranged, self.kind) {
+            (Some(ranged), _) if ranged.uses_ammunition => "Bow",
+            (Some(_), _) => "Throwing knives",
+            (None, WeaponKind::Dagger) => "Dagger",
+            (None, WeaponKind::Sword) => "Sword",
+            (None, WeaponKind::Axe) => "Axe",
+            (None, WeaponKind::Spear) => "Spear",
+        }
+    }
+
+    /// `attack` once the durability is taken into account.
+    pub fn effective_attack(&self) -> u32 {
+        if self.durability.is_worn_out() {
+            self.attack.div_ceil(2)
+        } else {
+            self.attack
+        }
+    }
+
+    /// Gold asked by the weapon shop to fully repair the weapon.
+    pub fn repair_cost(&self) -> u32 {
+        (self.durability.missing() * (self.attack + 1)).div_ceil(REPAIR_POINTS_PER_GOLD)
+    }
+
+    pub fn dagger(attack: u32) -> Self {
+        Self {
+            kind: WeaponKind::Dagger,
+            durability: Durability::new(60),
+            ..Self::new(attack, 0.4, 6., 12.)
+        }
+    }
+
+    pub fn sword(attack: u32) -> Self {
+        Self::new(attack, 1., 7., 20.)
+    }
+
+    pub fn axe(attack: u32) -> Self {
+        Self {
+            kind: WeaponKind::Axe,
+            durability: Durability::new(150),
+            ..Self::new(attack, 2., 8., 20.)
+        }
+    }
+
+    pub fn spear(attack: u32) -> Self {
+        Self {
+            kind: WeaponKind::Spear,
+            ..Self::new(attack, 1.4, 5., 30.)
+        }
+    }
+
+    pub fn bow(attack: u32) -> Self {
+        Self {
+            ranged: Some(RangedWeapon {
+                speed: 300.,
+                lifetime: 1.,
+                uses_ammunition: true,
+            }),
+            ..Self::new(attack, 0.5, 7., 20.)
+        }
+    }
+
+    pub fn throwing_knives(attack: u32) -> Self {
+        Self {
+            ranged: Some(RangedWeapon {
+                speed: 220.,
+                lifetime: 0.6,
+                uses_ammunition: false,
+            }),
+            ..Self::new(attack, 0.3, 5., 10.)
         }
     }
 }
@@ -50,63 +419,243 @@ pub fn update_notifications(
     }
 }
 
+/// Returns `true` if `receiver` belongs to a character which could be attacked.
 pub fn check_receivers(
     commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
     characters: &mut Query<(Entity, &mut Character, &Children)>,
-    attack: u32,
+    combat_events: &mut CombatEvents,
+    attacker: Attacker,
     attacker_id: Entity,
     attacker_kind: CharacterKind,
+    combat_rng: &mut CombatRng,
     receiver: &Entity,
-) {
+) -> bool {
     let (receiver_id, mut receiver) = match characters
         .iter_mut()
         .find(|(_, _, children)| children.contains(receiver))
     {
         Some((e, r, _)) => (e, r),
-        None => return,
+        None => return false,
     };
     eprintln!("found receiver {:?} {:?}", attacker_id, receiver_id);
     // If attacker_id == receiver_id, it means the character attacked itself so we ignore it.
-    // Also, we don't want monsters to attack their own.
-    if attacker_id != receiver_id && attacker_kind != receiver.kind {
-        let mut damage = attack.saturating_sub(receiver.stats.defense);
-        if damage < 1 {
-            damage = 1;
-        }
-        receiver.stats.health.subtract(damage as _);
+    // Also, we don't want monsters to attack their own nor to hit the dead.
+    if attacker_id == receiver_id
+        || attacker_kind == receiver.kind
+        || receiver.stats.health.is_empty()
+    {
+        return false;
+    }
+    deal_damage(
+        commands,
+        &mut combat_rng.0,
+        attacker,
+        attacker_id,
+        receiver_id,
+        &mut receiver,
+        combat_events,
+    );
+    true
+}
+
+/// Resolves the attack against `receiver` and removes the damage from its health. If it didn't
+/// miss, the receiver reacts to the hit, becomes invulnerable for a short time and the attacker
+/// effect is applied.
+///
+/// Everything else (notifications, death, XP...) is handled by the systems listening to the sent
+/// `AttackLanded`, `DamageDealt` and `CharacterDied` events.
+pub fn deal_damage<R: Rng>(
+    commands: &mut Commands,
+    rng: &mut R,
+    attacker: Attacker,
+    attacker_id: Entity,
+    receiver_id: Entity,
+    receiver: &mut Character,
+    combat_events: &mut CombatEvents,
+) {
+    if receiver.is_invulnerable() {
+        return;
+    }
+    let outcome = resolve_attack(rng, attacker, receiver);
+    combat_events.attacks.send(AttackLanded {
+        attacker: attacker_id,
+        receiver: receiver_id,
+        outcome,
+        is_magical: attacker.is_magical,
+    });
+    let damage = match outcome {
+        AttackOutcome::Miss => return,
+        AttackOutcome::Hit(damage) | AttackOutcome::Critical(damage) => damage,
+    };
+    receiver.stats.health.subtract(damage as _);
+    combat_events.damages.send(DamageDealt {
+        attacker: attacker_id,
+        receiver: receiver_id,
+        damage,
+    });
+    if receiver.stats.health.is_empty() {
+        combat_events.deaths.send(CharacterDied {
+            entity: receiver_id,
+            killer: Some(attacker_id),
+        });
+        return;
+    }
+    receiver.invulnerability_timer.reset();
+    commands.entity(receiver_id).insert(HitReaction {
+        from: attacker.origin,
+        timer: Timer::from_seconds(HIT_REACTION_TIME, TimerMode::Once),
+    });
+    if let Some(effect) = attacker.effect {
+        apply_status_effect(commands, receiver_id, effect);
+    }
+}
+
+/// Displays the outcome of the attacks above the characters which received them.
+pub fn show_attack_notifications(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut attacks: EventReader<AttackLanded>,
+    characters: Query<&Character>,
+) {
+    for attack in attacks.read() {
+        let receiver = match characters.get(attack.receiver) {
+            Ok(receiver) => receiver,
+            Err(_) => continue,
+        };
+        // No need to display anything above the dead.
         if receiver.stats.health.is_empty() {
-            // TODO: add xp to the killer
-            commands.entity(receiver_id).despawn_recursive();
+            continue;
+        }
+        let (text, color) = match attack.outcome {
+            AttackOutcome::Miss => ("Miss".to_owned(), LinearRgba::WHITE),
+            AttackOutcome::Hit(damage) => (damage.to_string(), LinearRgba::RED),
+            AttackOutcome::Critical(damage) => (format!("{}!", damage), CRITICAL_COLOR),
+        };
+        spawn_notification(
+            &mut commands,
+            &asset_server,
+            attack.receiver,
+            receiver.height,
+            &text,
+            color,
+        );
+    }
+}
+
+/// Wears the weapon of the characters which landed a hit. The weapon is lost once it breaks.
+pub fn wear_weapons(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut attacks: EventReader<AttackLanded>,
+    mut characters: Query<(&Character, &mut Inventory)>,
+) {
+    for attack in attacks.read() {
+        if attack.is_magical || attack.outcome == AttackOutcome::Miss {
+            continue;
+        }
+        let (character, mut inventory) = match characters.get_mut(attack.attacker) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let weapon = match inventory.equipped_weapon {
+            Some(ref mut weapon) => weapon,
+            None => continue,
+        };
+        let was_worn_out = weapon.durability.is_worn_out();
+        weapon.durability.wear();
+        let text = if weapon.durability.is_broken() {
+            inventory.equipped_weapon = None;
+            "Your weapon broke!"
+        } else if !was_worn_out && weapon.durability.is_worn_out() {
+            "Your weapon is worn out"
         } else {
-            let child = commands
-                .spawn((
-                    Text2dBundle {
-                        text: Text::from_section(
-                            damage.to_string().as_str(),
-                            TextStyle {
-                                font: asset_server.load(crate::FONT),
-                                font_size: 11.0,
-                                color: Color::LinearRgba(LinearRgba::RED),
-                            },
-                        )
-                        .with_justify(JustifyText::Center),
-                        transform: Transform::from_xyz(0., receiver.height / 2. + 8., 1.),
-                        ..default()
-                    },
-                    Notification {
-                        timer: Timer::from_seconds(NOTIFICATION_TIME, TimerMode::Once),
+            continue;
+        };
+        spawn_notification(
+            &mut commands,
+            &asset_server,
+            attack.attacker,
+            character.height,
+            text,
+            WEAR_COLOR,
+        );
+    }
+}
+
+/// Displays `text` above `entity` for a short time.
+pub fn spawn_notification(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    entity: Entity,
+    height: f32,
+    text: &str,
+    color: LinearRgba,
+) {
+    let child = commands
+        .spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    text,
+                    TextStyle {
+                        font: asset_server.load(crate::FONT),
+                        font_size: 11.0,
+                        color: Color::LinearRgba(color),
                     },
-                ))
-                .id();
-            commands.entity(receiver_id).add_child(child);
+                )
+                .with_justify(JustifyText::Center),
+                transform: Transform::from_xyz(0., height / 2. + 8., 1.),
+                ..default()
+            },
+            Notification {
+                timer: Timer::from_seconds(NOTIFICATION_TIME, TimerMode::Once),
+            },
+        ))
+        .id();
+    commands.entity(entity).add_child(child);
+}
+
+/// Pushes back the characters which were hit and makes them flash. It runs after the movement
+/// systems so the push back is added to the velocity they set.
+pub fn update_hit_reactions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut receivers: Query<(
+        Entity,
+        &Transform,
+        Option<&mut Velocity>,
+        &mut Sprite,
+        &mut HitReaction,
+    )>,
+) {
+    for (entity, transform, velocity, mut sprite, mut reaction) in receivers.iter_mut() {
+        if reaction.timer.tick(time.delta()).finished() {
+            sprite.color = Color::WHITE;
+            commands.entity(entity).remove::<HitReaction>();
+            continue;
         }
+        if let Some(mut velocity) = velocity {
+            let direction = (transform.translation.truncate() - reaction.from).normalize_or_zero();
+            velocity.linvel += direction * KNOCKBACK_SPEED * (1. - reaction.timer.fraction());
+        }
+        let blink = (reaction.timer.elapsed_secs() / FLASH_BLINK_TIME) as u32 % 2 == 0;
+        sprite.color = if blink {
+            Color::LinearRgba(FLASH_COLOR)
+        } else {
+            Color::WHITE
+        };
     }
 }
 
 #[derive(Component)]
 pub struct EntityDestroyer(Entity, Timer);
 
+impl EntityDestroyer {
+    /// `entity` will be despawned after `delay` seconds.
+    pub fn new(entity: Entity, delay: f32) -> Self {
+        Self(entity, Timer::from_seconds(delay, TimerMode::Once))
+    }
+}
+
 pub fn update_entity_destroyer(
     timer: Res<Time>,
     mut commands: Commands,
@@ -116,50 +665,15 @@ pub fn update_entity_destroyer(
     for (e, mut destroyer) in query.iter_mut() {
         destroyer.1.tick(delta);
         if destroyer.1.finished() {
-            commands.entity(destroyer.0).despawn_recursive();
+            // The entity might already be gone if its chunk was despawned in the meantime.
+            if let Some(entity) = commands.get_entity(destroyer.0) {
+                entity.despawn_recursive();
+            }
             commands.entity(e).despawn_recursive();
         }
     }
 }
 
-fn check_grass(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    grass: &mut Query<(Entity, &Grass, &mut Transform)>,
-    receiver: &Entity,
-) -> bool {
-    if let Ok((_, _, mut transform)) = grass.get_mut(*receiver) {
-        // We "remove" the existing grass...
-        commands.spawn(EntityDestroyer(
-            *receiver,
-            Timer::from_seconds(0.1, TimerMode::Once),
-        ));
-
-        // And replace it with a cut one.
-        let cut_grass = asset_server.load("textures/cut-grass.png");
-        commands.spawn((
-            crate::game::OutsideWorld,
-            SpriteBundle {
-                texture: cut_grass,
-                transform: transform.clone(),
-                sprite: Sprite {
-                    custom_size: Some(Vec2 {
-                        x: crate::GRASS_SIZE * 3. / 4.,
-                        y: crate::GRASS_SIZE * 3. / 4.,
-                    }),
-                    ..default()
-                },
-                ..default()
-            },
-        ));
-        // Moving grass outside of view.
-        transform.translation.y -= crate::MAP_SIZE * 3.;
-        true
-    } else {
-        false
-    }
-}
-
 macro_rules! getter {
     ($characters:ident, $weapons:ident, $x:ident, $y:ident) => {
         $characters
@@ -170,7 +684,8 @@ macro_rules! getter {
                     $weapons.iter().find(|(id, weapon)| children.contains(id))
                 {
                     Some((
-                        attacker.stats.attack + weapon.attack,
+                        attacker.stats.attack + weapon.effective_attack(),
+                        weapon.effect,
                         attacker_id,
                         $y,
                         attacker.kind,
@@ -185,46 +700,98 @@ macro_rules! getter {
 // This macro calls te same thing but inverts `x` and `y` to get attacker and receiver.
 macro_rules! get_attacker_and_receiver {
     ($characters:ident, $weapons:ident, $x:ident, $y:ident) => {
-        if let Some((attack, attacker_id, receiver, attacker_kind)) =
+        if let Some((attack, effect, attacker_id, receiver, attacker_kind)) =
             getter!($characters, $weapons, $x, $y)
         {
-            (attack, attacker_id, receiver, attacker_kind)
-        } else if let Some((attack, attacker_id, receiver, attacker_kind)) =
+            (attack, effect, attacker_id, receiver, attacker_kind)
+        } else if let Some((attack, effect, attacker_id, receiver, attacker_kind)) =
             getter!($characters, $weapons, $y, $x)
         {
-            (attack, attacker_id, receiver, attacker_kind)
+            (attack, effect, attacker_id, receiver, attacker_kind)
         } else {
             continue;
         }
     };
 }
 
+/// Resolves the attacks of the weapons and projectiles colliding with hitboxes (and grass). It
+/// doesn't do more than that: see `CombatEvents` and `GrassCut` for what happens next.
 pub fn handle_attacks(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
     mut collision_events: EventReader<CollisionEvent>,
     mut characters: Query<(Entity, &mut Character, &Children)>,
-    mut grass: Query<(Entity, &Grass, &mut Transform)>,
+    grass: Query<(), With<Grass>>,
+    mut combat_events: CombatEvents,
+    mut grass_cuts: EventWriter<GrassCut>,
+    mut combat_rng: ResMut<CombatRng>,
     weapons: Query<(Entity, &Weapon)>,
+    projectiles: Query<(Entity, &Projectile)>,
+    positions: Query<&GlobalTransform>,
 ) {
+    // A projectile can touch several hitboxes at once but only hits the first one.
+    let mut used_projectiles = Vec::new();
     for collision_event in collision_events.read() {
         if let CollisionEvent::Started(x, y, CollisionEventFlags::SENSOR) = collision_event {
-            let (attack, attacker_id, receiver, attacker_kind): (
+            let projectile = projectiles
+                .get(*x)
+                .map(|p| (p, y))
+                .or_else(|_| projectiles.get(*y).map(|p| (p, x)));
+            if let Ok(((projectile_id, projectile), receiver)) = projectile {
+                // The receiver is pushed away from the projectile rather than from the shooter.
+                let attacker = match positions.get(projectile_id) {
+                    Ok(pos) => Attacker {
+                        origin: pos.translation().truncate(),
+                        ..projectile.attacker
+                    },
+                    Err(_) => projectile.attacker,
+                };
+                if !used_projectiles.contains(&projectile_id)
+                    && check_receivers(
+                        &mut commands,
+                        &mut characters,
+                        &mut combat_events,
+                        attacker,
+                        projectile.shooter,
+                        projectile.shooter_kind,
+                        &mut combat_rng,
+                        receiver,
+                    )
+                {
+                    used_projectiles.push(projectile_id);
+                    commands.entity(projectile_id).despawn_recursive();
+                }
+                continue;
+            }
+            let (attack, effect, attacker_id, receiver, attacker_kind): (
                 u32,
+                Option<StatusEffect>,
                 Entity,
                 &Entity,
                 CharacterKind,
             ) = get_attacker_and_receiver!(characters, weapons, x, y);
             eprintln!("Found attacker");
-            if !check_grass(&mut commands, &asset_server, &mut grass, receiver) {
+            let attacker = match (characters.get(attacker_id), positions.get(attacker_id)) {
+                (Ok((_, attacker, _)), Ok(pos)) => {
+                    Attacker::new(attacker, attack, pos.translation().truncate())
+                        .with_effect(effect)
+                }
+                _ => continue,
+            };
+            if grass.contains(*receiver) {
+                grass_cuts.send(GrassCut {
+                    grass: *receiver,
+                    cutter: attacker_id,
+                });
+            } else {
                 // if the attack didn't cut grass, then it's very likely a `Character`.
                 check_receivers(
                     &mut commands,
-                    &asset_server,
                     &mut characters,
-                    attack,
+                    &mut combat_events,
+                    attacker,
                     attacker_id,
                     attacker_kind,
+                    &mut combat_rng,
                     receiver,
                 );
             }
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+
+use crate::game::GameState;
+
+/// How long (in seconds) a whole day lasts in the game.
+pub const DAY_LENGTH: f32 = 600.;
+/// Hour at which a new game starts.
+const START_HOUR: f32 = 8.;
+/// How bright it is in the middle of the night, between 0 and 1.
+const MIN_LIGHT: f32 = 0.35;
+const DAY_CLEAR_COLOR: LinearRgba = LinearRgba::rgb(0.27, 0.45, 0.2);
+const NIGHT_TINT: LinearRgba = LinearRgba::rgb(0.02, 0.03, 0.12);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayPeriod {
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+impl DayPeriod {
+    fn from_hour(hour: f32) -> Self {
+        if hour < 5. {
+            Self::Night
+        } else if hour < 7. {
+            Self::Dawn
+        } else if hour < 19. {
+            Self::Day
+        } else if hour < 21. {
+            Self::Dusk
+        } else {
+            Self::Night
+        }
+    }
+}
+
+/// Sent every time the `DayPeriod` changes so other systems can react to it.
+#[derive(Debug, Event)]
+pub struct DayPeriodChanged {
+    pub period: DayPeriod,
+    pub day: u32,
+}
+
+#[derive(Debug, Resource)]
+pub struct WorldClock {
+    /// How long (in seconds) a whole day lasts.
+    pub day_length: f32,
+    /// Number of days since the game started.
+    pub day: u32,
+    /// Time of the day, between 0 and 1 (0 is midnight and 0.5 is noon).
+    time: f32,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self {
+            day_length: DAY_LENGTH,
+            day: 1,
+            time: START_HOUR / 24.,
+        }
+    }
+}
+
+impl WorldClock {
+    /// The current hour, between 0 and 24.
+    pub fn hour(&self) -> f32 {
+        self.time * 24.
+    }
+
+    pub fn period(&self) -> DayPeriod {
+        DayPeriod::from_hour(self.hour())
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.period() == DayPeriod::Night
+    }
+
+    /// How bright it currently is, between `MIN_LIGHT` (midnight) and 1 (noon).
+    pub fn light(&self) -> f32 {
+        let sun = (1. - (self.time * std::f32::consts::TAU).cos()) / 2.;
+        // We want full light for most of the day, not only at noon.
+        MIN_LIGHT + (1. - MIN_LIGHT) * (sun * 1.6).min(1.)
+    }
+
+    /// Returns the time as displayed to the player.
+    pub fn display(&self) -> String {
+        let minutes = (self.time * 24. * 60.) as u32;
+        format!("Day {} - {:02}:{:02}", self.day, minutes / 60, minutes % 60)
+    }
+}
+
+pub fn update_world_clock(
+    time: Res<Time>,
+    mut clock: ResMut<WorldClock>,
+    mut events: EventWriter<DayPeriodChanged>,
+) {
+    let previous_period = clock.period();
+    clock.time += time.delta_seconds() / clock.day_length;
+    if clock.time >= 1. {
+        clock.time -= 1.;
+        clock.day += 1;
+    }
+    let period = clock.period();
+    if period != previous_period {
+        events.send(DayPeriodChanged {
+            period,
+            day: clock.day,
+        });
+    }
+}
+
+/// Used to darken everything (but the HUD) depending on the time of the day.
+#[derive(Component)]
+pub struct NightOverlay;
+
+pub fn spawn_night_overlay(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            // So it is displayed below the HUD.
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+        NightOverlay,
+    ));
+}
+
+pub fn update_tint(
+    clock: Res<WorldClock>,
+    game_state: Res<State<GameState>>,
+    mut clear_color: ResMut<ClearColor>,
+    mut overlay: Query<&mut BackgroundColor, With<NightOverlay>>,
+) {
+    let light = clock.light();
+    clear_color.0 = Color::LinearRgba(LinearRgba::rgb(
+        DAY_CLEAR_COLOR.red * light,
+        DAY_CLEAR_COLOR.green * light,
+        DAY_CLEAR_COLOR.blue * light,
+    ));
+    // There is no day or night inside buildings and dungeons.
+    let darkness = if *game_state == GameState::Outside {
+        1. - light
+    } else {
+        0.
+    };
+    for mut color in overlay.iter_mut() {
+        color.0 = Color::LinearRgba(NIGHT_TINT.with_alpha(darkness));
+    }
+}
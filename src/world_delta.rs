@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use bevy::utils::HashMap;
+
+use crate::map::Chunk;
+
+/// A change made to an environment object of the map. Only what the player can actually do to the
+/// environment is listed: for now, nothing can be removed from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The grass was cut and will grow back at `regrow_at` (compared to `Time::elapsed`).
+    GrassCut { regrow_at: Duration },
+}
+
+/// Changes made to the environment objects of a chunk, stored by position of the object.
+///
+/// Chunks are always generated the same way, so the position is enough to find back an object.
+#[derive(Debug, Default)]
+struct ChunkDelta {
+    changes: HashMap<IVec2, Change>,
+}
+
+/// Keeps track of what the player changed on the map so it can be replayed when a chunk is
+/// spawned again.
+#[derive(Debug, Default, Resource)]
+pub struct WorldDelta {
+    chunks: HashMap<Chunk, ChunkDelta>,
+}
+
+fn key(pos: Vec2) -> IVec2 {
+    pos.round().as_ivec2()
+}
+
+impl WorldDelta {
+    pub fn record(&mut self, chunk: Chunk, pos: Vec2, change: Change) {
+        self.chunks
+            .entry(chunk)
+            .or_default()
+            .changes
+            .insert(key(pos), change);
+    }
+
+    /// Removes the change recorded for the object at `pos`, if any.
+    pub fn forget(&mut self, chunk: Chunk, pos: Vec2) {
+        if let Some(delta) = self.chunks.get_mut(&chunk) {
+            delta.changes.remove(&key(pos));
+            if delta.changes.is_empty() {
+                self.chunks.remove(&chunk);
+            }
+        }
+    }
+
+    pub fn get(&self, chunk: Chunk, pos: Vec2) -> Option<Change> {
+        self.chunks
+            .get(&chunk)
+            .and_then(|delta| delta.changes.get(&key(pos)))
+            .copied()
+    }
+}